@@ -0,0 +1,714 @@
+#![allow(dead_code)]
+
+use crate::token::{self, Span};
+use std::fmt;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    None,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+// `&&` and `||` get a node of their own, as they don't always evaluate their right operand
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    IntDivide,
+    Power,
+    Equal,
+    NotEqual,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal {
+        value: Literal,
+        span: Span,
+    },
+    Variable {
+        name: String,
+        span: Span,
+    },
+    Grouping {
+        expr: Box<Expr>,
+        span: Span,
+    },
+    Unary {
+        operator: UnaryOp,
+        operand: Box<Expr>,
+        span: Span,
+    },
+    Binary {
+        left: Box<Expr>,
+        operator: BinaryOp,
+        right: Box<Expr>,
+        span: Span,
+    },
+    Logical {
+        left: Box<Expr>,
+        operator: LogicalOp,
+        right: Box<Expr>,
+        span: Span,
+    },
+    // `x = v`, or `x += v` and the like when there is an operator
+    Assign {
+        name: String,
+        operator: Option<BinaryOp>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    // `start..end`, end excluded
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+        span: Span,
+    },
+    Call {
+        callee: Box<Expr>,
+        args: Vec<Argument>,
+        span: Span,
+    },
+    // `"a #{x} b"`, the text and the embedded expressions in order
+    Interpolation {
+        parts: Vec<Expr>,
+        span: Span,
+    },
+    // `[a, b, c]`
+    List {
+        elements: Vec<Expr>,
+        span: Span,
+    },
+    // `{ "a": 1, b: 2 }`, identifier keys are strings too
+    Map {
+        entries: Vec<(String, Expr)>,
+        span: Span,
+    },
+    // `object[index]`, negative indices count from the end
+    Index {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        span: Span,
+    },
+    // `object[start:end]`, either bound may be left out
+    Slice {
+        object: Box<Expr>,
+        start: Option<Box<Expr>>,
+        end: Option<Box<Expr>>,
+        span: Span,
+    },
+    // `object[index] = value`, `object` is a variable or another index into one.
+    // With an operator, `object[index] += value` evaluates `object` and `index` once.
+    SetIndex {
+        object: Box<Expr>,
+        index: Box<Expr>,
+        operator: Option<BinaryOp>,
+        value: Box<Expr>,
+        span: Span,
+    },
+    // `object.name`, e.g. a member of a module
+    Get {
+        object: Box<Expr>,
+        name: String,
+        span: Span,
+    },
+    // `default` holds the body of the `none:` fallback arm
+    Case {
+        subject: Box<Expr>,
+        arms: Vec<CaseArm>,
+        default: Option<Vec<Stmt>>,
+        span: Span,
+    },
+    // `error("msg", data, kind: "io")` builds an error value without raising it
+    Error {
+        args: Vec<Argument>,
+        span: Span,
+    },
+    // runs `body`, and the first handler matching the kind of an error raised in it
+    Handle {
+        body: Vec<Stmt>,
+        handlers: Vec<ErrorHandler>,
+        span: Span,
+    },
+    // an `elsif` is an `if` nested as the only statement of the else branch
+    If {
+        condition: Box<Expr>,
+        then_branch: Vec<Stmt>,
+        else_branch: Option<Vec<Stmt>>,
+        span: Span,
+    },
+}
+
+// `1, 2: { ... }`: the body runs when the subject equals one of the patterns,
+// which are all literals
+#[derive(Debug, Clone, PartialEq)]
+pub struct CaseArm {
+    pub patterns: Vec<Expr>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+// `error "kind" e { ... }`, a handler without a kind catches every error
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorHandler {
+    pub kind: Option<String>,
+    pub binding: Option<ErrorBinding>,
+    pub body: Vec<Stmt>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ErrorBinding {
+    // `error e`: the error value itself
+    Value(String),
+    // `error (message, data, trace)`: its fields, in that order
+    Fields(Vec<String>),
+}
+
+// A call argument, either positional (`f(1)`) or named (`f(a: 1)`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Argument {
+    pub name: Option<String>,
+    pub value: Expr,
+    pub span: Span,
+}
+
+// A type name written after a `:`, e.g. the `int` in `a: int`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAnnotation {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub name: String,
+    pub type_annotation: Option<TypeAnnotation>,
+    pub span: Span,
+}
+
+// `def` declares a public function, `defp` one that is private to its module
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionDecl {
+    pub name: String,
+    pub params: Vec<Param>,
+    pub return_type: Option<TypeAnnotation>,
+    pub body: Vec<Stmt>,
+    pub public: bool,
+    // the `///` comments right above the declaration
+    pub doc: Option<String>,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Expression {
+        expr: Expr,
+        span: Span,
+    },
+    Print {
+        args: Vec<Expr>,
+        span: Span,
+    },
+    // `let` (mutable: false) and `var` (mutable: true) declarations
+    Let {
+        name: String,
+        mutable: bool,
+        initializer: Expr,
+        span: Span,
+    },
+    Block {
+        statements: Vec<Stmt>,
+        span: Span,
+    },
+    // shared with the function values created when the declaration runs
+    Function(Rc<FunctionDecl>),
+    Return {
+        value: Option<Expr>,
+        span: Span,
+    },
+    // `for item in xs { }` or `for (item, index) in xs { }`, `for (key, value) in m { }` for a map
+    For {
+        variable: String,
+        index: Option<String>,
+        iterable: Expr,
+        body: Vec<Stmt>,
+        span: Span,
+    },
+    Raise {
+        value: Expr,
+        span: Span,
+    },
+    Module {
+        name: String,
+        body: Vec<Stmt>,
+        doc: Option<String>,
+        span: Span,
+    },
+    // `import a.b` loads `a/b.rty` and binds it as `b`
+    Import {
+        path: Vec<String>,
+        span: Span,
+    },
+    Break {
+        span: Span,
+    },
+    Continue {
+        span: Span,
+    },
+}
+
+impl UnaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnaryOp::Negate => token::MINUS,
+            UnaryOp::Not => token::NOT,
+        }
+    }
+}
+
+impl LogicalOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LogicalOp::And => token::AND,
+            LogicalOp::Or => token::OR,
+        }
+    }
+}
+
+impl BinaryOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => token::PLUS,
+            BinaryOp::Subtract => token::MINUS,
+            BinaryOp::Multiply => token::MULTIPLY,
+            BinaryOp::Divide => token::DIVIDE,
+            BinaryOp::Modulo => token::MODULO,
+            BinaryOp::IntDivide => token::INT_DIVIDE,
+            BinaryOp::Power => token::POWER,
+            BinaryOp::Equal => token::EQ,
+            BinaryOp::NotEqual => token::NEQ,
+            BinaryOp::Greater => token::GREATER_THAN,
+            BinaryOp::GreaterEqual => token::GREATER_AND_EQ,
+            BinaryOp::Less => token::LESSER_THAN,
+            BinaryOp::LessEqual => token::LESSER_AND_EQ,
+        }
+    }
+
+    // the operator a compound assignment such as `+=` applies
+    pub fn from_compound_assignment(token_type: token::TokenType) -> Option<BinaryOp> {
+        match token_type {
+            token::TokenType::PlusAssign => Some(BinaryOp::Add),
+            token::TokenType::MinusAssign => Some(BinaryOp::Subtract),
+            token::TokenType::MultiplyAssign => Some(BinaryOp::Multiply),
+            token::TokenType::DivideAssign => Some(BinaryOp::Divide),
+            token::TokenType::ModuloAssign => Some(BinaryOp::Modulo),
+            _ => None,
+        }
+    }
+
+    pub fn from_token_type(token_type: token::TokenType) -> Option<BinaryOp> {
+        match token_type {
+            token::TokenType::Plus => Some(BinaryOp::Add),
+            token::TokenType::Minus => Some(BinaryOp::Subtract),
+            token::TokenType::Multiply => Some(BinaryOp::Multiply),
+            token::TokenType::Divide => Some(BinaryOp::Divide),
+            token::TokenType::Modulo => Some(BinaryOp::Modulo),
+            token::TokenType::IntDivide => Some(BinaryOp::IntDivide),
+            token::TokenType::Power => Some(BinaryOp::Power),
+            token::TokenType::Equal => Some(BinaryOp::Equal),
+            token::TokenType::NotEqual => Some(BinaryOp::NotEqual),
+            token::TokenType::GreaterThan => Some(BinaryOp::Greater),
+            token::TokenType::GreaterThanOrEqual => Some(BinaryOp::GreaterEqual),
+            token::TokenType::LesserThan => Some(BinaryOp::Less),
+            token::TokenType::LesserThanOrEqual => Some(BinaryOp::LessEqual),
+            _ => None,
+        }
+    }
+}
+
+impl Expr {
+    // a variable, or an element of a list or map held by one
+    pub fn is_assignment_target(&self) -> bool {
+        match self {
+            Expr::Variable { .. } => true,
+            Expr::Index { object, .. } | Expr::Get { object, .. } => object.is_assignment_target(),
+            _ => false,
+        }
+    }
+
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal { span, .. }
+            | Expr::Variable { span, .. }
+            | Expr::Grouping { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Assign { span, .. }
+            | Expr::Range { span, .. }
+            | Expr::Call { span, .. }
+            | Expr::Interpolation { span, .. }
+            | Expr::List { span, .. }
+            | Expr::Map { span, .. }
+            | Expr::Index { span, .. }
+            | Expr::Slice { span, .. }
+            | Expr::SetIndex { span, .. }
+            | Expr::Get { span, .. }
+            | Expr::Case { span, .. }
+            | Expr::Error { span, .. }
+            | Expr::Handle { span, .. }
+            | Expr::If { span, .. } => *span,
+        }
+    }
+}
+
+impl Stmt {
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Expression { span, .. }
+            | Stmt::Print { span, .. }
+            | Stmt::Let { span, .. }
+            | Stmt::Block { span, .. }
+            | Stmt::Return { span, .. }
+            | Stmt::For { span, .. }
+            | Stmt::Raise { span, .. }
+            | Stmt::Module { span, .. }
+            | Stmt::Import { span, .. }
+            | Stmt::Break { span }
+            | Stmt::Continue { span } => *span,
+            Stmt::Function(decl) => decl.span,
+        }
+    }
+}
+
+// Renders the tree as s-expressions, e.g. `1 + 2 * x` => `(+ 1 (* 2 x))`
+
+impl fmt::Display for Literal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Literal::Int(n) => write!(f, "{}", n),
+            // `{:?}` keeps the fraction of whole floats, `2.0` rather than `2`
+            Literal::Float(n) => write!(f, "{:?}", n),
+            Literal::String(s) => write!(f, "{:?}", s),
+            Literal::Bool(b) => write!(f, "{}", b),
+            Literal::None => write!(f, "{}", token::NONE),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expr::Literal { value, .. } => write!(f, "{}", value),
+            Expr::Variable { name, .. } => write!(f, "{}", name),
+            Expr::Grouping { expr, .. } => write!(f, "(group {})", expr),
+            Expr::Unary {
+                operator, operand, ..
+            } => write!(f, "({} {})", operator.as_str(), operand),
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => write!(f, "({} {} {})", operator.as_str(), left, right),
+            Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => write!(f, "({} {} {})", operator.as_str(), left, right),
+            Expr::Assign {
+                name,
+                operator,
+                value,
+                ..
+            } => write!(f, "({} {} {})", assign_label(operator), name, value),
+            Expr::Range { start, end, .. } => write!(f, "({} {} {})", token::DOT_DOT, start, end),
+            Expr::Call { callee, args, .. } => {
+                write!(f, "(call {}", callee)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Case {
+                subject,
+                arms,
+                default,
+                ..
+            } => {
+                write!(f, "({} {}", token::CASE, subject)?;
+                for arm in arms {
+                    let patterns: Vec<String> =
+                        arm.patterns.iter().map(|p| p.to_string()).collect();
+                    write!(f, " ")?;
+                    write_statements(f, &format!("({})", patterns.join(" ")), &arm.body)?;
+                }
+                if let Some(default) = default {
+                    write!(f, " ")?;
+                    write_statements(f, token::NONE, default)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Interpolation { parts, .. } => {
+                write!(f, "(str")?;
+                for part in parts {
+                    write!(f, " {}", part)?;
+                }
+                write!(f, ")")
+            }
+            Expr::List { elements, .. } => {
+                write!(f, "(list")?;
+                for element in elements {
+                    write!(f, " {}", element)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Map { entries, .. } => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " ({:?} {})", key, value)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Index { object, index, .. } => write!(f, "(index {} {})", object, index),
+            Expr::Slice {
+                object, start, end, ..
+            } => {
+                write!(f, "(slice {}", object)?;
+                for bound in [start, end] {
+                    match bound {
+                        Some(bound) => write!(f, " {}", bound)?,
+                        None => write!(f, " _")?,
+                    }
+                }
+                write!(f, ")")
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                operator,
+                value,
+                ..
+            } => write!(
+                f,
+                "({} (index {} {}) {})",
+                assign_label(operator),
+                object,
+                index,
+                value
+            ),
+            Expr::Get { object, name, .. } => write!(f, "({} {} {})", token::DOT, object, name),
+            Expr::Error { args, .. } => {
+                write!(f, "({}", token::ERROR)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Expr::Handle { body, handlers, .. } => {
+                write!(f, "({} ", token::HANDLE)?;
+                write_statements(f, "block", body)?;
+                for handler in handlers {
+                    write!(f, " ")?;
+                    write_statements(f, &handler.to_string(), &handler.body)?;
+                }
+                write!(f, ")")
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                write!(f, "({} {} ", token::IF, condition)?;
+                write_statements(f, "then", then_branch)?;
+                if let Some(else_branch) = else_branch {
+                    write!(f, " ")?;
+                    write_statements(f, token::ELSE, else_branch)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Stmt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stmt::Expression { expr, .. } => write!(f, "{}", expr),
+            Stmt::Print { args, .. } => {
+                write!(f, "({}", token::PRINT)?;
+                for arg in args {
+                    write!(f, " {}", arg)?;
+                }
+                write!(f, ")")
+            }
+            Stmt::Let {
+                name,
+                mutable,
+                initializer,
+                ..
+            } => {
+                let keyword = if *mutable { token::VAR } else { token::LET };
+                write!(f, "({} {} {})", keyword, name, initializer)
+            }
+            Stmt::Block { statements, .. } => write_statements(f, "block", statements),
+            Stmt::Function(decl) => write!(f, "{}", decl),
+            Stmt::Return { value, .. } => match value {
+                Some(value) => write!(f, "({} {})", token::RETURN, value),
+                None => write!(f, "({})", token::RETURN),
+            },
+            Stmt::For {
+                variable,
+                index,
+                iterable,
+                body,
+                ..
+            } => {
+                match index {
+                    Some(index) => {
+                        write!(f, "({} ({}, {}) {} ", token::FOR, variable, index, iterable)?
+                    }
+                    None => write!(f, "({} {} {} ", token::FOR, variable, iterable)?,
+                }
+                write_statements(f, "block", body)?;
+                write!(f, ")")
+            }
+            Stmt::Raise { value, .. } => write!(f, "({} {})", token::RAISE, value),
+            Stmt::Module { name, body, .. } => {
+                write_statements(f, &format!("{} {}", token::MODULE, name), body)
+            }
+            Stmt::Import { path, .. } => write!(f, "({} {})", token::IMPORT, path.join(token::DOT)),
+            Stmt::Break { .. } => write!(f, "({})", token::BREAK),
+            Stmt::Continue { .. } => write!(f, "({})", token::CONTINUE),
+        }
+    }
+}
+
+// `=`, or the compound assignment such as `+=`
+fn assign_label(operator: &Option<BinaryOp>) -> String {
+    match operator {
+        Some(operator) => format!("{}=", operator.as_str()),
+        None => token::ASSIGN.to_string(),
+    }
+}
+
+// `(label stmt1 stmt2 ...)`
+fn write_statements(f: &mut fmt::Formatter, label: &str, statements: &[Stmt]) -> fmt::Result {
+    write!(f, "({}", label)?;
+    for stmt in statements {
+        write!(f, " {}", stmt)?;
+    }
+    write!(f, ")")
+}
+
+// the handler head, e.g. `error "io" e`
+impl fmt::Display for ErrorHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", token::ERROR)?;
+        if let Some(kind) = &self.kind {
+            write!(f, " {:?}", kind)?;
+        }
+        match &self.binding {
+            Some(ErrorBinding::Value(name)) => write!(f, " {}", name),
+            Some(ErrorBinding::Fields(names)) => write!(f, " ({})", names.join(", ")),
+            None => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Argument {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.name {
+            Some(name) => write!(f, "{}: {}", name, self.value),
+            None => write!(f, "{}", self.value),
+        }
+    }
+}
+
+impl fmt::Display for TypeAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.type_annotation {
+            Some(t) => write!(f, "{}: {}", self.name, t),
+            None => write!(f, "{}", self.name),
+        }
+    }
+}
+
+impl fmt::Display for FunctionDecl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let keyword = if self.public { token::DEF } else { token::DEFP };
+        let params: Vec<String> = self.params.iter().map(|p| p.to_string()).collect();
+        write!(f, "({} {} ({})", keyword, self.name, params.join(", "))?;
+        if let Some(return_type) = &self.return_type {
+            write!(f, ": {}", return_type)?;
+        }
+        for stmt in &self.body {
+            write!(f, " {}", stmt)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn display_test() {
+        let expr = Expr::Binary {
+            left: Box::new(Expr::Unary {
+                operator: UnaryOp::Negate,
+                operand: Box::new(Expr::Literal {
+                    value: Literal::Int(1),
+                    span: Span::new(1, 2),
+                }),
+                span: Span::new(0, 2),
+            }),
+            operator: BinaryOp::Multiply,
+            right: Box::new(Expr::Grouping {
+                expr: Box::new(Expr::Variable {
+                    name: "x".to_string(),
+                    span: Span::new(6, 7),
+                }),
+                span: Span::new(5, 8),
+            }),
+            span: Span::new(0, 8),
+        };
+
+        assert_eq!("(* (- 1) (group x))", expr.to_string());
+        assert_eq!(Span::new(0, 8), expr.span());
+    }
+}
@@ -0,0 +1,976 @@
+use crate::ast::{
+    Argument, BinaryOp, ErrorBinding, Expr, FunctionDecl, Literal, Stmt, TypeAnnotation, UnaryOp,
+};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::methods;
+use crate::token::Span;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+
+// The type of an expression as far as it can be told without running it. Anything
+// that depends on runtime values, such as an unannotated parameter, is `Unknown`
+// and fits everywhere: only annotated code and literals are held to their types.
+#[derive(Debug, Clone)]
+pub enum Type {
+    Int,
+    Float,
+    String,
+    Bool,
+    None,
+    // the declaration, when the function is known
+    Function(Option<Rc<FunctionDecl>>),
+    Range,
+    List,
+    Map,
+    Error,
+    // the members, when the module is declared in the checked source
+    Module(Option<Rc<ModuleType>>),
+    Unknown,
+}
+
+#[derive(Debug)]
+pub struct ModuleType {
+    pub name: String,
+    pub members: HashMap<String, Type>,
+}
+
+impl Type {
+    // the name values of this type have at runtime, see `Value::type_name`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Type::Int => "int",
+            Type::Float => "float",
+            Type::String => "string",
+            Type::Bool => "bool",
+            Type::None => "none",
+            Type::Function(_) => "func",
+            Type::Range => "range",
+            Type::List => "list",
+            Type::Map => "map",
+            Type::Error => "error",
+            Type::Module(_) => "mod",
+            Type::Unknown => "any",
+        }
+    }
+
+    // the type name with its article, e.g. "an int"
+    pub fn a_name(&self) -> String {
+        match self {
+            Type::Int | Type::Error | Type::Unknown => format!("an {}", self.name()),
+            _ => format!("a {}", self.name()),
+        }
+    }
+
+    fn is_known(&self) -> bool {
+        !matches!(self, Type::Unknown)
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Type::Int | Type::Float)
+    }
+
+    // whether a value of type `actual` can be used where `self` is expected,
+    // ints are widened to floats as they are in arithmetic
+    fn accepts(&self, actual: &Type) -> bool {
+        match (self, actual) {
+            (Type::Unknown, _) | (_, Type::Unknown) => true,
+            (Type::Float, Type::Int) => true,
+            (expected, actual) => mem::discriminant(expected) == mem::discriminant(actual),
+        }
+    }
+
+    // the type of a value that was accepted as `self`: an int passes for a float
+    // without being converted, so a `float` may hold either
+    fn held(self) -> Type {
+        match self {
+            Type::Float => Type::Unknown,
+            t => t,
+        }
+    }
+
+    // the type of a value that is either of `self` or of `other`
+    fn join(self, other: Type) -> Type {
+        if mem::discriminant(&self) == mem::discriminant(&other) {
+            self
+        } else {
+            Type::Unknown
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// The type an annotation names, `byte` values are ints. Types are not values
+// yet, so a `type` may hold anything.
+fn annotated_type(name: &str) -> Option<Type> {
+    let t = match name {
+        "int" | "byte" => Type::Int,
+        "float" => Type::Float,
+        "string" => Type::String,
+        "bool" => Type::Bool,
+        "none" => Type::None,
+        "func" => Type::Function(None),
+        "range" => Type::Range,
+        "list" => Type::List,
+        "map" => Type::Map,
+        "error" => Type::Error,
+        "mod" => Type::Module(None),
+        "type" => Type::Unknown,
+        _ => return None,
+    };
+    Some(t)
+}
+
+// A statement that would fail at runtime whatever values flow through it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeError {
+    pub message: String,
+    pub span: Span,
+    pub hint: Option<String>,
+}
+
+impl TypeError {
+    pub fn to_diagnostic(&self, source: &str) -> Diagnostic {
+        let diagnostic =
+            Diagnostic::at_span(Severity::Error, self.message.clone(), self.span, source);
+        match &self.hint {
+            Some(hint) => diagnostic.with_hint(hint),
+            None => diagnostic,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Checker {
+    scopes: Vec<HashMap<String, Type>>, // innermost last
+    // names assigned to somewhere, a `var` of one of them can change its type
+    reassigned: HashSet<String>,
+    // each enclosing function with its return type, if annotated
+    returns: Vec<(String, Option<Type>)>,
+    errors: Vec<TypeError>,
+}
+
+impl Checker {
+    // Checks the statements without running them and reports every type error found.
+    // Bindings stay known to later calls, as they do in the interpreter.
+    pub fn check(&mut self, statements: &[Stmt]) -> Result<(), Vec<TypeError>> {
+        // a first pass finds the reassigned names, whose uses may come before the
+        // assignment in the source, e.g. in a loop
+        let scopes = self.scopes.clone();
+        self.check_statements(statements);
+        self.scopes = scopes;
+        self.errors.clear();
+
+        self.check_statements(statements);
+        if !self.errors.is_empty() {
+            return Err(mem::take(&mut self.errors));
+        }
+        Ok(())
+    }
+
+    // the type of the value of the last statement, as for a block
+    fn check_statements(&mut self, statements: &[Stmt]) -> Type {
+        let mut t = Type::None;
+        for stmt in statements {
+            t = self.check_statement(stmt);
+        }
+        t
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) -> Type {
+        self.scopes.push(HashMap::new());
+        let t = self.check_statements(statements);
+        self.scopes.pop();
+        t
+    }
+
+    fn check_statement(&mut self, stmt: &Stmt) -> Type {
+        match stmt {
+            Stmt::Expression { expr, .. } => self.check_expr(expr),
+            Stmt::Print { args, .. } => {
+                for arg in args {
+                    self.check_expr(arg);
+                }
+                Type::None
+            }
+            Stmt::Let {
+                name,
+                mutable,
+                initializer,
+                ..
+            } => {
+                let t = self.check_expr(initializer);
+                let t = if *mutable && self.reassigned.contains(name) {
+                    Type::Unknown
+                } else {
+                    t
+                };
+                self.define(name, t);
+                Type::None
+            }
+            Stmt::Block { statements, .. } => self.check_block(statements),
+            Stmt::Function(decl) => {
+                // defined first, so that the body can call it
+                self.define(&decl.name, Type::Function(Some(decl.clone())));
+                self.check_function(decl);
+                Type::None
+            }
+            Stmt::Return { value, span } => {
+                let t = match value {
+                    Some(value) => self.check_expr(value),
+                    None => Type::None,
+                };
+                let span = value.as_ref().map_or(*span, |value| value.span());
+                if let Some((function, Some(expected))) = self.returns.last().cloned() {
+                    self.check_return(&function, &expected, &t, span);
+                }
+                Type::Unknown
+            }
+            Stmt::For {
+                variable,
+                index,
+                iterable,
+                body,
+                ..
+            } => {
+                let iterable_type = self.check_expr(iterable);
+                // a map binds the value of each key to the second name, others the position
+                let second = match iterable_type {
+                    Type::Map | Type::Unknown => Type::Unknown,
+                    _ => Type::Int,
+                };
+                let item = match iterable_type {
+                    Type::Range => Type::Int,
+                    Type::String => Type::String,
+                    // map keys
+                    Type::Map => Type::String,
+                    Type::List | Type::Unknown => Type::Unknown,
+                    t => {
+                        self.error(
+                            format!("cannot iterate over {}", t.a_name()),
+                            iterable.span(),
+                        );
+                        Type::Unknown
+                    }
+                };
+                self.scopes.push(HashMap::new());
+                self.define(variable, item);
+                if let Some(index) = index {
+                    self.define(index, second);
+                }
+                self.check_block(body);
+                self.scopes.pop();
+                Type::None
+            }
+            Stmt::Raise { value, span } => {
+                let t = self.check_expr(value);
+                if !matches!(t, Type::Error | Type::String | Type::Unknown) {
+                    self.error(
+                        format!("can only raise errors and strings, found {}", t),
+                        *span,
+                    );
+                }
+                Type::Unknown
+            }
+            Stmt::Module { name, body, .. } => {
+                self.scopes.push(HashMap::new());
+                self.check_statements(body);
+                let members = self.scopes.pop().unwrap_or_default();
+                let module = ModuleType {
+                    name: name.clone(),
+                    members,
+                };
+                self.define(name, Type::Module(Some(Rc::new(module))));
+                Type::None
+            }
+            // imported files are checked on their own, by the interpreter before it runs them
+            Stmt::Import { path, .. } => {
+                self.define(&path[path.len() - 1], Type::Module(None));
+                Type::None
+            }
+            Stmt::Break { .. } | Stmt::Continue { .. } => Type::Unknown,
+        }
+    }
+
+    // The body sees the parameters with their annotated types, and its value as
+    // well as every `return` in it must fit the return type
+    fn check_function(&mut self, decl: &FunctionDecl) {
+        let mut scope = HashMap::new();
+        for param in &decl.params {
+            let t = match &param.type_annotation {
+                Some(annotation) => self.annotation(annotation).held(),
+                None => Type::Unknown,
+            };
+            scope.insert(param.name.clone(), t);
+        }
+        let return_type = decl
+            .return_type
+            .as_ref()
+            .map(|annotation| self.annotation(annotation));
+
+        self.scopes.push(scope);
+        self.returns.push((decl.name.clone(), return_type.clone()));
+        let t = self.check_statements(&decl.body);
+        self.returns.pop();
+        self.scopes.pop();
+
+        if let Some(expected) = return_type {
+            let span = decl.body.last().map_or(decl.span, |last| last.span());
+            self.check_return(&decl.name, &expected, &t, span);
+        }
+    }
+
+    fn check_return(&mut self, function: &str, expected: &Type, found: &Type, span: Span) {
+        if !expected.accepts(found) {
+            self.error(
+                format!(
+                    "'{}' must return {}, found {}",
+                    function,
+                    expected.a_name(),
+                    found.a_name()
+                ),
+                span,
+            );
+        }
+    }
+
+    fn annotation(&mut self, annotation: &TypeAnnotation) -> Type {
+        match annotated_type(&annotation.name) {
+            Some(t) => t,
+            None => {
+                self.errors.push(TypeError {
+                    message: format!("unknown type '{}'", annotation.name),
+                    span: annotation.span,
+                    hint: Some(
+                        "types are int, float, byte, string, bool, none, func, mod, type, list, map, range and error"
+                            .to_string(),
+                    ),
+                });
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_expr(&mut self, expr: &Expr) -> Type {
+        match expr {
+            Expr::Literal { value, .. } => match value {
+                Literal::Int(_) => Type::Int,
+                Literal::Float(_) => Type::Float,
+                Literal::String(_) => Type::String,
+                Literal::Bool(_) => Type::Bool,
+                Literal::None => Type::None,
+            },
+            // undefined names are left to the interpreter, they may be defined by then
+            Expr::Variable { name, .. } => self.lookup(name),
+            Expr::Grouping { expr, .. } => self.check_expr(expr),
+            Expr::Assign {
+                name,
+                operator,
+                value,
+                span,
+            } => {
+                self.reassigned.insert(name.clone());
+                let value = self.check_expr(value);
+                let t = match operator {
+                    Some(operator) => {
+                        let current = self.lookup(name);
+                        self.binary(*operator, current, value, *span)
+                    }
+                    None => value,
+                };
+                self.widen(name, t.clone());
+                t
+            }
+            Expr::Unary {
+                operator,
+                operand,
+                span,
+            } => {
+                let t = self.check_expr(operand);
+                match operator {
+                    UnaryOp::Not => Type::Bool,
+                    UnaryOp::Negate if t.is_number() || !t.is_known() => t,
+                    UnaryOp::Negate => {
+                        self.error(
+                            format!("operand of '-' must be a number, found {}", t),
+                            *span,
+                        );
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                let left = self.check_expr(left);
+                let right = self.check_expr(right);
+                self.binary(*operator, left, right, *span)
+            }
+            Expr::Logical { left, right, .. } => {
+                self.check_expr(left);
+                self.check_expr(right);
+                Type::Bool
+            }
+            Expr::Range { start, end, span } => {
+                let start = self.check_expr(start);
+                let end = self.check_expr(end);
+                if !Type::Int.accepts(&start) || !Type::Int.accepts(&end) {
+                    self.error(
+                        format!("range bounds must be ints, found {} and {}", start, end),
+                        *span,
+                    );
+                }
+                Type::Range
+            }
+            Expr::Call { callee, args, span } => self.call(callee, args, *span),
+            Expr::Interpolation { parts, .. } => {
+                for part in parts {
+                    self.check_expr(part);
+                }
+                Type::String
+            }
+            Expr::List { elements, .. } => {
+                for element in elements {
+                    self.check_expr(element);
+                }
+                Type::List
+            }
+            Expr::Map { entries, .. } => {
+                for (_, value) in entries {
+                    self.check_expr(value);
+                }
+                Type::Map
+            }
+            Expr::Index { object, index, .. } => {
+                let object = self.check_expr(object);
+                let key = self.check_expr(index);
+                match object {
+                    Type::List | Type::String | Type::Map => {
+                        self.check_key(&object, &key, index.span())
+                    }
+                    Type::Unknown => {}
+                    ref t => self.error(format!("cannot index into {}", t.a_name()), index.span()),
+                }
+                match object {
+                    Type::String => Type::String,
+                    _ => Type::Unknown,
+                }
+            }
+            Expr::Slice {
+                object,
+                start,
+                end,
+                span,
+            } => {
+                let object = self.check_expr(object);
+                for bound in [start, end].into_iter().flatten() {
+                    let t = self.check_expr(bound);
+                    if !Type::Int.accepts(&t) {
+                        self.error(
+                            format!("slice bounds must be ints, found {}", t),
+                            bound.span(),
+                        );
+                    }
+                }
+                match object {
+                    Type::List | Type::String | Type::Unknown => object,
+                    t => {
+                        self.error(format!("cannot slice {}", t.a_name()), *span);
+                        Type::Unknown
+                    }
+                }
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                operator,
+                value,
+                span,
+            } => {
+                let object = self.check_expr(object);
+                let key = self.check_expr(index);
+                match object {
+                    Type::List | Type::Map => self.check_key(&object, &key, index.span()),
+                    Type::Unknown => {}
+                    t => self.error(
+                        format!("cannot assign to an element of {}", t.a_name()),
+                        index.span(),
+                    ),
+                }
+                let value = self.check_expr(value);
+                match operator {
+                    // elements may hold anything
+                    Some(operator) => self.binary(*operator, Type::Unknown, value, *span),
+                    None => value,
+                }
+            }
+            Expr::Get { object, name, span } => {
+                let object = self.check_expr(object);
+                self.member(object, name, *span)
+            }
+            Expr::Case {
+                subject,
+                arms,
+                default,
+                ..
+            } => {
+                self.check_expr(subject);
+                let mut t = None;
+                for arm in arms {
+                    for pattern in &arm.patterns {
+                        self.check_expr(pattern);
+                    }
+                    let arm = self.check_block(&arm.body);
+                    t = Some(t.map_or(arm.clone(), |t: Type| t.join(arm)));
+                }
+                let default = match default {
+                    Some(body) => self.check_block(body),
+                    None => Type::None,
+                };
+                t.map_or(default.clone(), |t| t.join(default))
+            }
+            Expr::Error { args, .. } => {
+                for arg in args {
+                    self.check_expr(&arg.value);
+                }
+                Type::Error
+            }
+            Expr::Handle { body, handlers, .. } => {
+                let mut t = self.check_block(body);
+                for handler in handlers {
+                    self.scopes.push(HashMap::new());
+                    match &handler.binding {
+                        Some(ErrorBinding::Value(name)) => self.define(name, Type::Error),
+                        // message, data and trace
+                        Some(ErrorBinding::Fields(names)) => {
+                            let fields = [Type::String, Type::Unknown, Type::String];
+                            for (name, field) in names.iter().zip(fields) {
+                                self.define(name, field);
+                            }
+                        }
+                        None => {}
+                    }
+                    t = t.join(self.check_block(&handler.body));
+                    self.scopes.pop();
+                }
+                t
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                self.check_expr(condition);
+                let then_type = self.check_block(then_branch);
+                let else_type = match else_branch {
+                    Some(else_branch) => self.check_block(else_branch),
+                    None => Type::None,
+                };
+                then_type.join(else_type)
+            }
+        }
+    }
+
+    // The same rules as the interpreter applies to the operand values
+    fn binary(&mut self, operator: BinaryOp, left: Type, right: Type, span: Span) -> Type {
+        let known = left.is_known() && right.is_known();
+        match operator {
+            BinaryOp::Equal | BinaryOp::NotEqual => Type::Bool,
+            BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Less | BinaryOp::LessEqual => {
+                let comparable = (left.is_number() && right.is_number())
+                    || matches!((&left, &right), (Type::String, Type::String));
+                if known && !comparable {
+                    self.error(
+                        format!(
+                            "cannot compare {} with {} using '{}'",
+                            left,
+                            right,
+                            operator.as_str()
+                        ),
+                        span,
+                    );
+                }
+                Type::Bool
+            }
+            BinaryOp::Add if matches!((&left, &right), (Type::String, Type::String)) => {
+                Type::String
+            }
+            _ if !known => match operator {
+                BinaryOp::Divide => Type::Float,
+                BinaryOp::IntDivide => Type::Int,
+                _ => Type::Unknown,
+            },
+            _ if !left.is_number() || !right.is_number() => {
+                let message = match operator {
+                    BinaryOp::Add => format!(
+                        "operands of '+' must be two numbers or two strings, found {} and {}",
+                        left, right
+                    ),
+                    _ => format!(
+                        "operands of '{}' must be numbers, found {} and {}",
+                        operator.as_str(),
+                        left,
+                        right
+                    ),
+                };
+                self.error(message, span);
+                Type::Unknown
+            }
+            BinaryOp::Divide => Type::Float,
+            BinaryOp::IntDivide => Type::Int,
+            // a negative exponent gives a float
+            BinaryOp::Power if matches!((&left, &right), (Type::Int, Type::Int)) => Type::Unknown,
+            _ if matches!((&left, &right), (Type::Int, Type::Int)) => Type::Int,
+            _ => Type::Float,
+        }
+    }
+
+    fn check_key(&mut self, object: &Type, key: &Type, span: Span) {
+        match object {
+            Type::Map if !Type::String.accepts(key) => {
+                self.error(format!("map keys must be strings, found {}", key), span)
+            }
+            Type::List | Type::String if !Type::Int.accepts(key) => {
+                self.error(format!("index must be an int, found {}", key), span)
+            }
+            _ => {}
+        }
+    }
+
+    // `object.name` of a map or a module
+    fn member(&mut self, object: Type, name: &str, span: Span) -> Type {
+        match object {
+            Type::Map | Type::Unknown | Type::Module(None) => Type::Unknown,
+            Type::Module(Some(module)) => match module.members.get(name) {
+                Some(Type::Function(Some(decl))) if !decl.public => {
+                    self.error(
+                        format!("'{}' is private to module '{}'", name, module.name),
+                        span,
+                    );
+                    Type::Unknown
+                }
+                Some(t) => t.clone(),
+                None => {
+                    self.error(
+                        format!("module '{}' has no member '{}'", module.name, name),
+                        span,
+                    );
+                    Type::Unknown
+                }
+            },
+            t => {
+                self.error(format!("cannot access '{}' on {}", name, t.a_name()), span);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn call(&mut self, callee: &Expr, args: &[Argument], span: Span) -> Type {
+        let callee_type = match callee {
+            Expr::Get {
+                object,
+                name,
+                span: member_span,
+            } => {
+                let receiver = self.check_expr(object);
+                if let Some(method) = methods::find(receiver.name(), name) {
+                    self.check_arguments(args);
+                    if let Some(arg) = args.iter().find(|arg| arg.name.is_some()) {
+                        self.error(
+                            format!("'{}' does not take named arguments", name),
+                            arg.span,
+                        );
+                    } else if args.len() != method.arity {
+                        self.error(
+                            format!(
+                                "'{}' expects {} argument(s), found {}",
+                                name,
+                                method.arity,
+                                args.len()
+                            ),
+                            span,
+                        );
+                    }
+                    return Type::Unknown;
+                }
+                // maps and modules may hold a function of that name
+                if receiver.is_known() && !matches!(receiver, Type::Map | Type::Module(_)) {
+                    self.check_arguments(args);
+                    self.error(
+                        format!("{} has no method '{}'", receiver, name),
+                        *member_span,
+                    );
+                    return Type::Unknown;
+                }
+                self.member(receiver, name, *member_span)
+            }
+            callee => self.check_expr(callee),
+        };
+
+        let arg_types = self.check_arguments(args);
+        match callee_type {
+            Type::Function(Some(decl)) => self.check_call(&decl, args, arg_types, span),
+            Type::Function(None) | Type::Unknown => Type::Unknown,
+            t => {
+                self.error(
+                    format!("cannot call {}, only functions are callable", t.a_name()),
+                    callee.span(),
+                );
+                Type::Unknown
+            }
+        }
+    }
+
+    fn check_arguments(&mut self, args: &[Argument]) -> Vec<Type> {
+        args.iter().map(|arg| self.check_expr(&arg.value)).collect()
+    }
+
+    // Matches the arguments to the parameters of `decl` the way the interpreter
+    // does, and checks them against the parameter annotations
+    fn check_call(
+        &mut self,
+        decl: &FunctionDecl,
+        args: &[Argument],
+        arg_types: Vec<Type>,
+        span: Span,
+    ) -> Type {
+        let return_type = match &decl.return_type {
+            Some(annotation) => annotated_type(&annotation.name).map_or(Type::Unknown, Type::held),
+            None => Type::Unknown,
+        };
+        if args.len() != decl.params.len() {
+            self.error(
+                format!(
+                    "'{}' expects {} argument(s), found {}",
+                    decl.name,
+                    decl.params.len(),
+                    args.len()
+                ),
+                span,
+            );
+            return return_type;
+        }
+
+        let mut given = vec![false; decl.params.len()];
+        for (position, (arg, t)) in args.iter().zip(arg_types).enumerate() {
+            let index = match &arg.name {
+                None => position,
+                Some(name) => match decl.params.iter().position(|param| &param.name == name) {
+                    Some(index) => index,
+                    None => {
+                        self.error(
+                            format!("'{}' has no parameter named '{}'", decl.name, name),
+                            arg.span,
+                        );
+                        continue;
+                    }
+                },
+            };
+
+            let param = &decl.params[index];
+            if mem::replace(&mut given[index], true) {
+                self.error(
+                    format!(
+                        "argument '{}' of '{}' is given more than once",
+                        param.name, decl.name
+                    ),
+                    arg.span,
+                );
+            }
+            let expected = param
+                .type_annotation
+                .as_ref()
+                .and_then(|annotation| annotated_type(&annotation.name));
+            if let Some(expected) = expected {
+                if !expected.accepts(&t) {
+                    self.error(
+                        format!(
+                            "argument '{}' of '{}' must be {}, found {}",
+                            param.name,
+                            decl.name,
+                            expected.a_name(),
+                            t.a_name()
+                        ),
+                        arg.value.span(),
+                    );
+                }
+            }
+        }
+        return_type
+    }
+
+    fn define(&mut self, name: &str, t: Type) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), t);
+        }
+    }
+
+    // An assignment to a binding checked earlier, e.g. on a previous line of the
+    // REPL, leaves it with either type
+    fn widen(&mut self, name: &str, t: Type) {
+        if let Some(binding) = self
+            .scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+        {
+            *binding = mem::replace(binding, Type::Unknown).join(t);
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or(Type::Unknown)
+    }
+
+    fn error(&mut self, message: String, span: Span) {
+        self.errors.push(TypeError {
+            message,
+            span,
+            hint: None,
+        });
+    }
+}
+
+pub fn new() -> Checker {
+    Checker {
+        scopes: vec![HashMap::new()],
+        reassigned: HashSet::new(),
+        returns: Vec::new(),
+        errors: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+    use crate::lexer;
+    use crate::parser;
+
+    fn check(input: &str) -> Vec<String> {
+        let tokens = lexer::new(input).parse().unwrap();
+        let statements = parser::new(tokens).parse().unwrap();
+        match new().check(&statements) {
+            Ok(()) => Vec::new(),
+            Err(errors) => errors.into_iter().map(|err| err.message).collect(),
+        }
+    }
+
+    #[test]
+    fn annotation_test() {
+        let program = "def add(a: int, b: int): int { a + b }\n";
+        assert!(check(&format!("{}add(1, 2)\nadd(b: 1, a: 2)", program)).is_empty());
+        assert_eq!(
+            vec![
+                "argument 'b' of 'add' must be an int, found a string",
+                "'add' expects 2 argument(s), found 1",
+                "'add' has no parameter named 'c'",
+            ],
+            check(&format!(
+                "{}add(1, \"2\")\nadd(1)\nadd(a: 1, c: 2)",
+                program
+            ))
+        );
+
+        // ints fit where a float is expected, not the other way round
+        assert!(check("def half(x: float): float { x / 2 }\nhalf(3)").is_empty());
+        assert_eq!(
+            vec![
+                "'f' must return an int, found a float",
+                "'g' must return a string, found an int"
+            ],
+            check("def f(): int { 1.5 }\ndef g(x): string {\n  if x { return 1 }\n  \"a\"\n}")
+        );
+        assert!(check("def f(x: type) { x }").is_empty());
+        assert_eq!(vec!["unknown type 'kind'"], check("def f(x: kind) { x }"));
+
+        // an int returned or passed as a float stays an int
+        assert!(check("def f(): float { 1 }\nlet xs = [10, 20]\nxs[f()]").is_empty());
+        assert!(check("def g(x: float) { [10, 20][x] }\ng(1)").is_empty());
+    }
+
+    #[test]
+    fn operator_test() {
+        assert_eq!(
+            vec![
+                "operands of '+' must be two numbers or two strings, found int and string",
+                "operands of '*' must be numbers, found bool and int",
+                "operand of '-' must be a number, found string",
+                "cannot compare int with string using '<'",
+                "range bounds must be ints, found int and float",
+            ],
+            check("1 + \"a\"\ntrue * 2\n-\"a\"\n1 < \"a\"\n0..1.5")
+        );
+        // the result types carry on to later expressions
+        assert_eq!(
+            vec!["operands of '-' must be numbers, found string and int"],
+            check("let s = \"a\" + \"b\"\nlet n = 7 ~/ 2\ns - n")
+        );
+        assert!(check("let x = 1 / 2\nx + 1.5\n\"#{x}\" + \"!\"").is_empty());
+    }
+
+    #[test]
+    fn dynamic_code_test() {
+        // unannotated parameters and reassigned vars may hold anything
+        assert!(check("def f(a, b) { a + b }\nf(1, \"2\")").is_empty());
+        assert!(check(
+            "var total = none\nfor i in 0..3 {\n  if total != none { total = total + i } else { total = 0 }\n}"
+        )
+        .is_empty());
+        assert!(check("let m = { f: 1 }\nm.f()\nundefined_name + 1").is_empty());
+
+        // as in the REPL, a var assigned on a later line may hold either type
+        let mut checker = new();
+        let mut check_line = |line: &str| {
+            let statements = parser::new(lexer::new(line).parse().unwrap())
+                .parse()
+                .unwrap();
+            checker.check(&statements)
+        };
+        assert_eq!(Ok(()), check_line("var x = 1"));
+        assert_eq!(Ok(()), check_line("x = \"a\""));
+        assert_eq!(Ok(()), check_line("x + \"b\""));
+        assert_eq!(Ok(()), check_line("let y = 2"));
+        assert!(check_line("y - \"b\"").is_err());
+    }
+
+    #[test]
+    fn member_test() {
+        let program = "module m {\n  defp hidden() { 1 }\n  def shown(x: int) { x }\n}\n";
+        assert!(check(&format!("{}m.shown(2)", program)).is_empty());
+        assert_eq!(
+            vec![
+                "argument 'x' of 'shown' must be an int, found a string",
+                "'hidden' is private to module 'm'",
+                "module 'm' has no member 'missing'",
+            ],
+            check(&format!("{}m.shown(\"a\")\nm.hidden()\nm.missing", program))
+        );
+        assert_eq!(
+            vec![
+                "'push' expects 1 argument(s), found 2",
+                "list has no method 'size'",
+                "int has no method 'push'",
+                "cannot call an int, only functions are callable",
+                "cannot iterate over a bool",
+                "index must be an int, found string",
+            ],
+            check("var xs = [1]\nxs.push(1, 2)\nxs.size()\nlet n = 1\nn.push(2)\nn()\nfor b in true { }\nxs[\"a\"]")
+        );
+    }
+}
@@ -0,0 +1,184 @@
+#![allow(dead_code)]
+
+use crate::token::Span;
+use std::fmt;
+
+#[derive(strum_macros::Display, Debug, Clone, Copy, PartialEq, Eq)]
+#[strum(serialize_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+// A problem found in the source, positioned so it can be reported back to the user.
+// `line` and `column` are 1-based, `span` covers the offending text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub span: Span,
+    pub hint: Option<String>,
+    pub file: Option<String>, // set when the problem is not in the file being run
+}
+
+impl Diagnostic {
+    pub fn error(message: String, line: usize, column: usize, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Error,
+            message,
+            line,
+            column,
+            span,
+            hint: None,
+            file: None,
+        }
+    }
+
+    pub fn warning(message: String, line: usize, column: usize, span: Span) -> Diagnostic {
+        Diagnostic {
+            severity: Severity::Warning,
+            ..Diagnostic::error(message, line, column, span)
+        }
+    }
+
+    // Builds a diagnostic for `span`, working out its line and column from `source`
+    pub fn at_span(severity: Severity, message: String, span: Span, source: &str) -> Diagnostic {
+        let (line, column) = line_and_column(source, span.start);
+        Diagnostic {
+            severity,
+            ..Diagnostic::error(message, line, column, span)
+        }
+    }
+
+    pub fn with_hint(mut self, hint: &str) -> Diagnostic {
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    pub fn with_file(mut self, file: &str) -> Diagnostic {
+        self.file = Some(file.to_string());
+        self
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    // Renders the diagnostic together with the offending source line, e.g.
+    //
+    // error: unrecognized character '$'
+    //  --> line 1, col 5
+    //   |
+    // 1 | x = $2
+    //   |     ^
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("{}: {}\n --> ", self.severity, self.message);
+        if let Some(file) = &self.file {
+            out.push_str(&format!("{}, ", file));
+        }
+        out.push_str(&format!("line {}, col {}\n", self.line, self.column));
+
+        if let Some(text) = source.lines().nth(self.line.saturating_sub(1)) {
+            let gutter = " ".repeat(self.line.to_string().len());
+            let line_len = text.chars().count();
+            let start = self.column.saturating_sub(1).min(line_len);
+            let span_len = match source.get(self.span.start..self.span.end) {
+                Some(text) => text.chars().count(),
+                None => 1,
+            };
+            let width = span_len.min(line_len.saturating_sub(start)).max(1);
+
+            out.push_str(&format!("{} |\n", gutter));
+            out.push_str(&format!("{} | {}\n", self.line, text));
+            out.push_str(&format!(
+                "{} | {}{}\n",
+                gutter,
+                " ".repeat(start),
+                "^".repeat(width)
+            ));
+        }
+
+        if let Some(hint) = &self.hint {
+            out.push_str(&format!(" = hint: {}\n", hint));
+        }
+
+        out
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} at line {}, col {}",
+            self.severity, self.message, self.line, self.column
+        )
+    }
+}
+
+// 1-based line and column of the character at byte `offset`
+pub fn line_and_column(source: &str, offset: usize) -> (usize, usize) {
+    let mut line = 1;
+    let mut column = 1;
+    for (_, c) in source.char_indices().take_while(|(i, _)| *i < offset) {
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn render_test() {
+        let source = "let x = 1\nlet y = $2\n";
+        let diagnostic = Diagnostic::error(
+            "unrecognized character '$'".to_string(),
+            2,
+            9,
+            Span::new(18, 19),
+        )
+        .with_hint("remove it");
+
+        let expected = "error: unrecognized character '$'
+ --> line 2, col 9
+  |
+2 | let y = $2
+  |         ^
+ = hint: remove it
+";
+        assert_eq!(expected, diagnostic.render(source));
+
+        let diagnostic = diagnostic.with_file("lib/util.rty");
+        assert!(diagnostic
+            .render(source)
+            .starts_with("error: unrecognized character '$'\n --> lib/util.rty, line 2, col 9\n"));
+    }
+
+    #[test]
+    fn at_span_test() {
+        let source = "1 +\n  \"abc\" * 2";
+        let diagnostic = Diagnostic::at_span(
+            Severity::Warning,
+            "odd".to_string(),
+            Span::new(6, 11),
+            source,
+        );
+
+        assert_eq!((2, 3), (diagnostic.line, diagnostic.column));
+        assert!(!diagnostic.is_error());
+        assert!(diagnostic
+            .render(source)
+            .ends_with("2 |   \"abc\" * 2\n  |   ^^^^^\n"));
+        assert_eq!("warning: odd at line 2, col 3", diagnostic.to_string());
+    }
+}
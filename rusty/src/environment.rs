@@ -0,0 +1,127 @@
+use crate::value::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+// A single scope of variable bindings. Scopes are chained through `enclosing`,
+// so a lookup walks outwards until the name is found or the global scope is
+// exhausted.
+#[derive(Debug, Default)]
+pub struct Environment {
+    values: HashMap<String, Binding>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
+}
+
+#[derive(Debug)]
+struct Binding {
+    value: Value,
+    mutable: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssignError {
+    Undefined,
+    Immutable,
+}
+
+impl Environment {
+    // Declares `name` in this scope, shadowing any outer binding with the same name
+    pub fn define(&mut self, name: &str, value: Value, mutable: bool) {
+        self.values
+            .insert(name.to_string(), Binding { value, mutable });
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(binding) => Some(binding.value.clone()),
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow().get(name),
+                None => None,
+            },
+        }
+    }
+
+    // the bindings declared directly in this scope
+    pub fn local_values(&self) -> Vec<(String, Value)> {
+        self.values
+            .iter()
+            .map(|(name, binding)| (name.clone(), binding.value.clone()))
+            .collect()
+    }
+
+    // Changes the value of a mutable binding in place, e.g. one element of a list
+    pub fn update<T>(
+        &mut self,
+        name: &str,
+        change: impl FnOnce(&mut Value) -> T,
+    ) -> Result<T, AssignError> {
+        match self.values.get_mut(name) {
+            Some(binding) if !binding.mutable => Err(AssignError::Immutable),
+            Some(binding) => Ok(change(&mut binding.value)),
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().update(name, change),
+                None => Err(AssignError::Undefined),
+            },
+        }
+    }
+
+    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), AssignError> {
+        match self.values.get_mut(name) {
+            Some(binding) if !binding.mutable => Err(AssignError::Immutable),
+            Some(binding) => {
+                binding.value = value;
+                Ok(())
+            }
+            None => match &self.enclosing {
+                Some(enclosing) => enclosing.borrow_mut().assign(name, value),
+                None => Err(AssignError::Undefined),
+            },
+        }
+    }
+}
+
+pub fn new() -> Environment {
+    Environment::default()
+}
+
+pub fn new_enclosed(enclosing: Rc<RefCell<Environment>>) -> Environment {
+    Environment {
+        values: HashMap::new(),
+        enclosing: Some(enclosing),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn scope_test() {
+        let globals = Rc::new(RefCell::new(new()));
+        globals.borrow_mut().define("x", Value::Int(1), true);
+        globals.borrow_mut().define("y", Value::Int(2), false);
+
+        let mut local = new_enclosed(globals.clone());
+        local.define("y", Value::Int(3), true);
+
+        assert_eq!(Some(Value::Int(1)), local.get("x"));
+        assert_eq!(Some(Value::Int(3)), local.get("y"));
+        assert_eq!(None, local.get("z"));
+
+        // assignment updates the binding in the scope that owns it
+        assert_eq!(Ok(()), local.assign("x", Value::Int(5)));
+        assert_eq!(Some(Value::Int(5)), globals.borrow().get("x"));
+
+        // the shadowing `var y` is assignable, the outer `let y` is not
+        assert_eq!(Ok(()), local.assign("y", Value::Int(4)));
+        assert_eq!(
+            Err(AssignError::Immutable),
+            globals.borrow_mut().assign("y", Value::Int(4))
+        );
+        assert_eq!(
+            Err(AssignError::Undefined),
+            local.assign("z", Value::Int(4))
+        );
+    }
+}
@@ -0,0 +1,2179 @@
+use crate::ast::{
+    Argument, BinaryOp, ErrorBinding, ErrorHandler, Expr, Literal, LogicalOp, Stmt, UnaryOp,
+};
+use crate::checker;
+use crate::diagnostic::Diagnostic;
+use crate::environment::{self, AssignError, Environment};
+use crate::lexer;
+use crate::methods;
+use crate::parser;
+use crate::token::{self, Span};
+use crate::value::{Function, Map, Module, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+// kind of the errors raised by the interpreter itself, e.g. on a type mismatch
+pub const RUNTIME_ERROR: &str = "runtime";
+// kind of the `error(...)` values created without one
+pub const DEFAULT_ERROR: &str = "error";
+// kind of the error raised when calls nest deeper than MAX_CALL_DEPTH
+pub const RECURSION_ERROR: &str = "recursion";
+
+// How deeply function calls may nest. Each call takes a good deal of native stack,
+// the thread running the interpreter needs room for this many, see main.rs.
+pub const MAX_CALL_DEPTH: usize = 1000;
+
+// An error raised while running a script, either by the interpreter or with `raise`.
+// `trace` lists the function calls it unwound through, innermost first.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeError {
+    pub kind: String,
+    pub message: String,
+    pub data: Value,
+    pub span: Span,
+    pub file: Option<Rc<SourceFile>>, // where `span` points, none for the main script
+    pub trace: Vec<Frame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub function: String,
+    pub span: Span, // of the call
+    pub file: Option<Rc<SourceFile>>,
+}
+
+// An imported script, kept around so that errors inside it can be shown
+#[derive(Debug, PartialEq)]
+pub struct SourceFile {
+    pub path: PathBuf,
+    pub text: String,
+}
+
+impl RuntimeError {
+    // the message shown to the user, prefixed with the kind of errors scripts define
+    pub fn describe(&self) -> String {
+        if self.kind == RUNTIME_ERROR || self.kind == DEFAULT_ERROR {
+            return self.message.clone();
+        }
+        format!("{}: {}", self.kind, self.message)
+    }
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at col {}", self.message, self.span.start + 1)
+    }
+}
+
+pub fn runtime_error(message: String, span: Span) -> RuntimeError {
+    RuntimeError {
+        kind: RUNTIME_ERROR.to_string(),
+        message,
+        data: Value::None,
+        span,
+        file: None,
+        trace: Vec::new(),
+    }
+}
+
+// The indices leading from a variable down to one of its elements, with their spans
+type ElementPath = Vec<(Value, Span)>;
+
+// Why execution of a statement stopped early: an error, or a `return`,
+// `break` or `continue` travelling up to the call or loop that handles it
+#[derive(Debug)]
+enum Unwind {
+    Error(RuntimeError),
+    Return(Value),
+    Break,
+    Continue,
+}
+
+impl From<RuntimeError> for Unwind {
+    fn from(err: RuntimeError) -> Unwind {
+        Unwind::Error(err)
+    }
+}
+
+pub struct Interpreter {
+    environment: Rc<RefCell<Environment>>,
+    file: Option<Rc<SourceFile>>, // of the code being run, none for the main script
+    search_path: Vec<PathBuf>,    // directories `import` looks in, in order
+    modules: HashMap<PathBuf, Value>,
+    importing: Vec<String>, // modules whose import is under way, to detect cycles
+    depth: usize,           // how many function calls are under way
+}
+
+impl Interpreter {
+    // Runs the statements in order and returns the value of the last one
+    pub fn interpret(&mut self, statements: &[Stmt]) -> Result<Value, RuntimeError> {
+        let mut value = Value::None;
+        for stmt in statements {
+            value = match self.execute(stmt) {
+                Ok(value) => value,
+                Err(Unwind::Error(err)) => return Err(err),
+                // the parser rejects `return`, `break` and `continue` at the top level
+                Err(Unwind::Return(value)) => value,
+                Err(Unwind::Break) | Err(Unwind::Continue) => Value::None,
+            };
+        }
+        Ok(value)
+    }
+
+    pub fn set_search_path(&mut self, search_path: Vec<PathBuf>) {
+        self.search_path = search_path;
+    }
+
+    // The source of the statements run next, e.g. a line of the REPL. Functions
+    // they declare keep it, so their errors are shown against that text.
+    pub fn set_file(&mut self, file: Option<Rc<SourceFile>>) {
+        self.file = file;
+    }
+
+    // Calls `function` with already evaluated arguments. The value of a
+    // `return`, or else of the last statement in the body, is the result.
+    pub fn call_function(
+        &mut self,
+        function: &Function,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        if args.len() != function.arity() {
+            return Err(arity_error(function, args.len(), span));
+        }
+        // a catchable error rather than overflowing the native stack
+        if self.depth == MAX_CALL_DEPTH {
+            return Err(RuntimeError {
+                kind: RECURSION_ERROR.to_string(),
+                ..runtime_error(
+                    format!(
+                        "calls nested more than {} deep in '{}'",
+                        MAX_CALL_DEPTH,
+                        function.name()
+                    ),
+                    span,
+                )
+            });
+        }
+
+        let mut scope = environment::new_enclosed(function.closure.clone());
+        for (param, arg) in function.declaration.params.iter().zip(args) {
+            scope.define(&param.name, arg, false);
+        }
+
+        let caller_file = std::mem::replace(&mut self.file, function.file.clone());
+        self.depth += 1;
+        let result = self.execute_block(&function.declaration.body, scope);
+        self.depth -= 1;
+        self.file = caller_file;
+
+        match result {
+            Ok(value) | Err(Unwind::Return(value)) => Ok(value),
+            Err(Unwind::Error(mut err)) => {
+                // an error that hasn't left a function yet was raised in this one
+                if err.trace.is_empty() {
+                    err.file = function.file.clone();
+                }
+                err.trace.push(Frame {
+                    function: function.name().to_string(),
+                    span,
+                    file: self.file.clone(),
+                });
+                Err(err)
+            }
+            // the parser rejects `break` and `continue` outside of a loop body
+            Err(Unwind::Break) | Err(Unwind::Continue) => Ok(Value::None),
+        }
+    }
+
+    // Loads `path` (e.g. `a.b` from `a/b.rty`) from the first directory of the search
+    // path that has it. Each file is run once, later imports share its module.
+    fn import(&mut self, path: &[String], span: Span) -> Result<Value, RuntimeError> {
+        let name = path.join(".");
+        if let Some(position) = self.importing.iter().position(|m| *m == name) {
+            let cycle = self.importing[position..].join(" -> ");
+            return Err(runtime_error(
+                format!("import cycle: {} -> {}", cycle, name),
+                span,
+            ));
+        }
+
+        let relative = path.iter().collect::<PathBuf>().with_extension("rty");
+        let file_path = self
+            .search_path
+            .iter()
+            .map(|dir| dir.join(&relative))
+            .find(|candidate| candidate.is_file());
+        let file_path = match file_path {
+            Some(file_path) => file_path,
+            None => {
+                let dirs: Vec<String> = self
+                    .search_path
+                    .iter()
+                    .map(|dir| dir.display().to_string())
+                    .collect();
+                return Err(runtime_error(
+                    format!(
+                        "cannot find module '{}': no {} in {}",
+                        name,
+                        relative.display(),
+                        dirs.join(", ")
+                    ),
+                    span,
+                ));
+            }
+        };
+        if let Some(module) = self.modules.get(&file_path) {
+            return Ok(module.clone());
+        }
+
+        let text = fs::read_to_string(&file_path).map_err(|err| {
+            runtime_error(format!("cannot read module '{}': {}", name, err), span)
+        })?;
+        let file = Rc::new(SourceFile {
+            path: file_path.clone(),
+            text,
+        });
+        let frame = Frame {
+            function: format!("import {}", name),
+            span,
+            file: self.file.clone(),
+        };
+
+        let statements = match compile(&file.text) {
+            Ok(statements) => statements,
+            Err(diagnostic) => {
+                return Err(RuntimeError {
+                    file: Some(file.clone()),
+                    trace: vec![frame],
+                    ..runtime_error(
+                        format!("syntax error in module '{}': {}", name, diagnostic.message),
+                        diagnostic.span,
+                    )
+                })
+            }
+        };
+
+        // a module is type checked on its own, it sees nothing of the importer
+        if let Err(errors) = checker::new().check(&statements) {
+            return Err(RuntimeError {
+                file: Some(file.clone()),
+                trace: vec![frame],
+                ..runtime_error(
+                    format!("type error in module '{}': {}", name, errors[0].message),
+                    errors[0].span,
+                )
+            });
+        }
+
+        // the module runs in a global scope of its own
+        let scope = Rc::new(RefCell::new(environment::new()));
+        self.importing.push(name);
+        let importer_file = self.file.replace(file.clone());
+        let result = self.execute_in(&statements, scope.clone());
+        self.file = importer_file;
+        self.importing.pop();
+
+        match result {
+            Err(Unwind::Error(mut err)) => {
+                if err.trace.is_empty() {
+                    err.file = Some(file);
+                }
+                err.trace.push(frame);
+                return Err(err);
+            }
+            // the parser rejects these at the top level
+            Ok(_) | Err(Unwind::Return(_)) | Err(Unwind::Break) | Err(Unwind::Continue) => {}
+        }
+
+        let module = module_value(&path[path.len() - 1], &scope.borrow());
+        self.modules.insert(file_path, module.clone());
+        Ok(module)
+    }
+
+    // The variable an assignment target starts from, and the indices leading
+    // from it to the element, e.g. `("m", ["xs", 0])` for `m.xs[0]`
+    fn target_path<'e>(&mut self, target: &'e Expr) -> Result<(&'e str, ElementPath), Unwind> {
+        match target {
+            Expr::Variable { name, .. } => Ok((name, Vec::new())),
+            Expr::Index { object, index, .. } => {
+                let (name, mut indices) = self.target_path(object)?;
+                indices.push((self.evaluate(index)?, index.span()));
+                Ok((name, indices))
+            }
+            Expr::Get { object, name, span } => {
+                let (variable, mut indices) = self.target_path(object)?;
+                indices.push((Value::String(name.clone()), *span));
+                Ok((variable, indices))
+            }
+            expr => Err(runtime_error(
+                "only elements of a variable can be changed".to_string(),
+                expr.span(),
+            )
+            .into()),
+        }
+    }
+
+    // `object.name(args)` for a method that changes `object` in place, e.g. `xs.push(4)`.
+    // Modules, and maps without such a method, call their member `name` instead.
+    fn call_mutating_method(
+        &mut self,
+        object: &Expr,
+        name: &str,
+        member_span: Span,
+        args: &[Argument],
+        span: Span,
+    ) -> Result<Value, Unwind> {
+        let (variable, indices) = self.target_path(object)?;
+        let receiver = self.environment.borrow().get(variable);
+        if let Some(Ok(receiver)) = receiver.map(|root| receiver_at(root, &indices)) {
+            let has_method = methods::lookup(&receiver, name).is_some();
+            if matches!(receiver, Value::Module(_))
+                || matches!(receiver, Value::Map(_)) && !has_method
+            {
+                let function = member(receiver, name, member_span)?;
+                return self.call_value(function, member_span, args, span);
+            }
+        }
+        let values = self.method_arguments(name, args)?;
+
+        let result = self.environment.borrow_mut().update(variable, |root| {
+            let receiver = element_mut(root, &indices)?;
+            match methods::lookup(receiver, name) {
+                Some(method) => method.call(receiver, values, span),
+                None => Err(runtime_error(
+                    format!("{} has no method '{}'", receiver.type_name(), name),
+                    span,
+                )),
+            }
+        });
+        match result {
+            Ok(result) => Ok(result?),
+            Err(err) => Err(modify_error(err, variable, object.span()).into()),
+        }
+    }
+
+    // methods of built-in types only take positional arguments
+    fn method_arguments(&mut self, name: &str, args: &[Argument]) -> Result<Vec<Value>, Unwind> {
+        let mut values = Vec::new();
+        for arg in args {
+            if arg.name.is_some() {
+                return Err(runtime_error(
+                    format!("'{}' does not take named arguments", name),
+                    arg.span,
+                )
+                .into());
+            }
+            values.push(self.evaluate(&arg.value)?);
+        }
+        Ok(values)
+    }
+
+    // `error(message, data, kind)`, every argument can also be given by name
+    fn error_value(&mut self, args: &[Argument], span: Span) -> Result<Value, Unwind> {
+        const FIELDS: [&str; 3] = ["message", "data", "kind"];
+
+        let mut fields: [Option<Value>; 3] = [None, None, None];
+        for (position, arg) in args.iter().enumerate() {
+            let index = match &arg.name {
+                None if position < FIELDS.len() => position,
+                None => {
+                    return Err(runtime_error(
+                        format!(
+                            "'error' expects at most 3 arguments (message, data, kind), found {}",
+                            args.len()
+                        ),
+                        span,
+                    )
+                    .into())
+                }
+                Some(name) => match FIELDS.iter().position(|field| field == name) {
+                    Some(index) => index,
+                    None => {
+                        return Err(runtime_error(
+                            format!("'error' has no parameter named '{}'", name),
+                            arg.span,
+                        )
+                        .into())
+                    }
+                },
+            };
+            if fields[index].is_some() {
+                return Err(runtime_error(
+                    format!(
+                        "argument '{}' of 'error' is given more than once",
+                        FIELDS[index]
+                    ),
+                    arg.span,
+                )
+                .into());
+            }
+            fields[index] = Some(self.evaluate(&arg.value)?);
+        }
+
+        let [message, data, kind] = fields;
+        let message = match message {
+            Some(Value::String(message)) => message,
+            Some(value) => {
+                return Err(runtime_error(
+                    format!(
+                        "error message must be a string, found {}",
+                        value.type_name()
+                    ),
+                    span,
+                )
+                .into())
+            }
+            None => return Err(runtime_error("'error' needs a message".to_string(), span).into()),
+        };
+        let kind = match kind {
+            Some(Value::String(kind)) => kind,
+            None => DEFAULT_ERROR.to_string(),
+            Some(value) => {
+                return Err(runtime_error(
+                    format!("error kind must be a string, found {}", value.type_name()),
+                    span,
+                )
+                .into())
+            }
+        };
+
+        Ok(Value::Error(Rc::new(RuntimeError {
+            kind,
+            message,
+            data: data.unwrap_or(Value::None),
+            span,
+            file: self.file.clone(),
+            trace: Vec::new(),
+        })))
+    }
+
+    // Runs the first handler whose kind matches `err`, or passes the error on
+    fn handle_error(
+        &mut self,
+        err: RuntimeError,
+        handlers: &[ErrorHandler],
+    ) -> Result<Value, Unwind> {
+        let handler = handlers.iter().find(|handler| match &handler.kind {
+            Some(kind) => *kind == err.kind,
+            None => true,
+        });
+        let handler = match handler {
+            Some(handler) => handler,
+            None => return Err(Unwind::Error(err)),
+        };
+
+        let mut scope = environment::new_enclosed(self.environment.clone());
+        match &handler.binding {
+            Some(ErrorBinding::Value(name)) => {
+                scope.define(name, Value::Error(Rc::new(err)), false);
+            }
+            Some(ErrorBinding::Fields(names)) => {
+                let trace: Vec<String> = err
+                    .trace
+                    .iter()
+                    .map(|frame| format!("at {}", frame.function))
+                    .collect();
+                let fields = [
+                    Value::String(err.message),
+                    err.data,
+                    Value::String(trace.join("\n")),
+                ];
+                for (name, value) in names.iter().zip(fields) {
+                    scope.define(name, value, false);
+                }
+            }
+            None => {}
+        }
+        self.execute_block(&handler.body, scope)
+    }
+
+    fn execute(&mut self, stmt: &Stmt) -> Result<Value, Unwind> {
+        match stmt {
+            Stmt::Expression { expr, .. } => self.evaluate(expr),
+            Stmt::Print { args, .. } => {
+                let mut values = Vec::new();
+                for arg in args {
+                    values.push(self.evaluate(arg)?.to_string());
+                }
+                println!("{}", values.join(" "));
+                Ok(Value::None)
+            }
+            Stmt::Let {
+                name,
+                mutable,
+                initializer,
+                ..
+            } => {
+                let value = self.evaluate(initializer)?;
+                self.environment.borrow_mut().define(name, value, *mutable);
+                Ok(Value::None)
+            }
+            Stmt::Block { statements, .. } => {
+                let scope = environment::new_enclosed(self.environment.clone());
+                self.execute_block(statements, scope)
+            }
+            Stmt::Function(declaration) => {
+                let function = Function {
+                    declaration: declaration.clone(),
+                    closure: self.environment.clone(),
+                    file: self.file.clone(),
+                };
+                self.environment.borrow_mut().define(
+                    &declaration.name,
+                    Value::Function(Rc::new(function)),
+                    false,
+                );
+                Ok(Value::None)
+            }
+            Stmt::Return { value, .. } => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::None,
+                };
+                Err(Unwind::Return(value))
+            }
+            Stmt::For {
+                variable,
+                index,
+                iterable,
+                body,
+                ..
+            } => {
+                let items = iterate(self.evaluate(iterable)?, iterable.span())?;
+                for (item, second) in items {
+                    let mut scope = environment::new_enclosed(self.environment.clone());
+                    scope.define(variable, item, false);
+                    if let Some(index) = index {
+                        scope.define(index, second, false);
+                    }
+
+                    match self.execute_block(body, scope) {
+                        Ok(_) | Err(Unwind::Continue) => {}
+                        Err(Unwind::Break) => break,
+                        Err(unwind) => return Err(unwind),
+                    }
+                }
+                Ok(Value::None)
+            }
+            Stmt::Raise { value, span } => {
+                let mut err = match self.evaluate(value)? {
+                    Value::Error(err) => (*err).clone(),
+                    Value::String(message) => RuntimeError {
+                        kind: DEFAULT_ERROR.to_string(),
+                        ..runtime_error(message, *span)
+                    },
+                    value => {
+                        return Err(runtime_error(
+                            format!(
+                                "can only raise errors and strings, found {}",
+                                value.type_name()
+                            ),
+                            *span,
+                        )
+                        .into())
+                    }
+                };
+                err.span = *span;
+                err.file = self.file.clone();
+                Err(Unwind::Error(err))
+            }
+            Stmt::Module { name, body, .. } => {
+                let scope = Rc::new(RefCell::new(environment::new_enclosed(
+                    self.environment.clone(),
+                )));
+                self.execute_in(body, scope.clone())?;
+
+                let module = module_value(name, &scope.borrow());
+                self.environment.borrow_mut().define(name, module, false);
+                Ok(Value::None)
+            }
+            Stmt::Import { path, span } => {
+                let module = self.import(path, *span)?;
+                self.environment
+                    .borrow_mut()
+                    .define(&path[path.len() - 1], module, false);
+                Ok(Value::None)
+            }
+            Stmt::Break { .. } => Err(Unwind::Break),
+            Stmt::Continue { .. } => Err(Unwind::Continue),
+        }
+    }
+
+    // Runs `statements` inside `scope`, restoring the current scope afterwards
+    // even when one of them fails
+    fn execute_block(&mut self, statements: &[Stmt], scope: Environment) -> Result<Value, Unwind> {
+        self.execute_in(statements, Rc::new(RefCell::new(scope)))
+    }
+
+    // Like execute_block, for callers that need the scope once the statements ran
+    fn execute_in(
+        &mut self,
+        statements: &[Stmt],
+        scope: Rc<RefCell<Environment>>,
+    ) -> Result<Value, Unwind> {
+        let previous = std::mem::replace(&mut self.environment, scope);
+
+        let mut result = Ok(Value::None);
+        for stmt in statements {
+            result = self.execute(stmt);
+            if result.is_err() {
+                break;
+            }
+        }
+
+        self.environment = previous;
+        result
+    }
+
+    fn evaluate(&mut self, expr: &Expr) -> Result<Value, Unwind> {
+        match expr {
+            Expr::Literal { value, .. } => Ok(literal_value(value)),
+            Expr::Variable { name, span } => match self.environment.borrow().get(name) {
+                Some(value) => Ok(value),
+                None => Err(runtime_error(format!("undefined variable '{}'", name), *span).into()),
+            },
+            Expr::Assign {
+                name,
+                operator,
+                value,
+                span,
+            } => {
+                let current = match operator {
+                    Some(_) => Some(self.evaluate(&Expr::Variable {
+                        name: name.clone(),
+                        span: *span,
+                    })?),
+                    None => None,
+                };
+                let mut value = self.evaluate(value)?;
+                if let (Some(operator), Some(current)) = (operator, current) {
+                    value = binary(*operator, current, value, *span)?;
+                }
+                let result = self.environment.borrow_mut().assign(name, value.clone());
+                match result {
+                    Ok(()) => Ok(value),
+                    Err(AssignError::Undefined) => Err(runtime_error(
+                        format!("cannot assign to undeclared variable '{}'", name),
+                        *span,
+                    )
+                    .into()),
+                    Err(AssignError::Immutable) => Err(runtime_error(
+                        format!(
+                            "cannot reassign '{}', it was declared with let (use var for mutable bindings)",
+                            name
+                        ),
+                        *span,
+                    )
+                    .into()),
+                }
+            }
+            Expr::SetIndex {
+                object,
+                index,
+                operator,
+                value,
+                span,
+            } => {
+                let (name, mut indices) = self.target_path(object)?;
+                indices.push((self.evaluate(index)?, index.span()));
+
+                let value = self.evaluate(value)?;
+                let result = self.environment.borrow_mut().update(name, |target| {
+                    // the element is read through the path evaluated above, not a second time
+                    let value = match operator {
+                        Some(operator) => {
+                            binary(*operator, get_element(target, &indices)?, value, *span)?
+                        }
+                        None => value,
+                    };
+                    set_element(target, &indices, value.clone()).map(|_| value)
+                });
+                match result {
+                    Ok(result) => Ok(result?),
+                    Err(err) => Err(modify_error(err, name, *span).into()),
+                }
+            }
+            Expr::Grouping { expr, .. } => self.evaluate(expr),
+            Expr::Unary {
+                operator,
+                operand,
+                span,
+            } => {
+                let operand = self.evaluate(operand)?;
+                Ok(unary(*operator, operand, *span)?)
+            }
+            Expr::Binary {
+                left,
+                operator,
+                right,
+                span,
+            } => {
+                let left = self.evaluate(left)?;
+                let right = self.evaluate(right)?;
+                Ok(binary(*operator, left, right, *span)?)
+            }
+            Expr::Case {
+                subject,
+                arms,
+                default,
+                ..
+            } => {
+                let subject = self.evaluate(subject)?;
+                let mut chosen = default.as_ref();
+                'arms: for arm in arms {
+                    for pattern in &arm.patterns {
+                        if self.evaluate(pattern)? == subject {
+                            chosen = Some(&arm.body);
+                            break 'arms;
+                        }
+                    }
+                }
+
+                match chosen {
+                    Some(body) => {
+                        let scope = environment::new_enclosed(self.environment.clone());
+                        self.execute_block(body, scope)
+                    }
+                    None => Ok(Value::None),
+                }
+            }
+            // every value is shown the way `print` shows it
+            Expr::Interpolation { parts, .. } => {
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::String(text))
+            }
+            Expr::List { elements, .. } => {
+                let mut items = Vec::with_capacity(elements.len());
+                for element in elements {
+                    items.push(self.evaluate(element)?);
+                }
+                Ok(Value::List(Rc::new(items)))
+            }
+            Expr::Map { entries, .. } => {
+                let mut map = Map::default();
+                for (key, value) in entries {
+                    // a literal keeps the entries it is written with, none ones included
+                    map.insert(key.clone(), self.evaluate(value)?);
+                }
+                Ok(Value::Map(Rc::new(map)))
+            }
+            Expr::Index { object, index, .. } => {
+                let object = self.evaluate(object)?;
+                let position = self.evaluate(index)?;
+                Ok(element(object, &position, index.span())?)
+            }
+            Expr::Slice {
+                object,
+                start,
+                end,
+                span,
+            } => {
+                let object = self.evaluate(object)?;
+                let start = match start {
+                    Some(start) => Some(self.evaluate(start)?),
+                    None => None,
+                };
+                let end = match end {
+                    Some(end) => Some(self.evaluate(end)?),
+                    None => None,
+                };
+                Ok(slice(object, start, end, *span)?)
+            }
+            Expr::Get { object, name, span } => Ok(member(self.evaluate(object)?, name, *span)?),
+            Expr::Error { args, span } => self.error_value(args, *span),
+            Expr::Handle { body, handlers, .. } => {
+                let scope = environment::new_enclosed(self.environment.clone());
+                match self.execute_block(body, scope) {
+                    Err(Unwind::Error(err)) => self.handle_error(err, handlers),
+                    result => result,
+                }
+            }
+            Expr::If {
+                condition,
+                then_branch,
+                else_branch,
+                ..
+            } => {
+                let branch = if self.evaluate(condition)?.is_truthy() {
+                    then_branch
+                } else {
+                    match else_branch {
+                        Some(else_branch) => else_branch,
+                        None => return Ok(Value::None),
+                    }
+                };
+                let scope = environment::new_enclosed(self.environment.clone());
+                self.execute_block(branch, scope)
+            }
+            Expr::Range { start, end, span } => {
+                let start = self.evaluate(start)?;
+                let end = self.evaluate(end)?;
+                match (as_int(&start), as_int(&end)) {
+                    (Some(start), Some(end)) => Ok(Value::Range(start, end)),
+                    _ => Err(runtime_error(
+                        format!("range bounds must be ints, found {} and {}", start, end),
+                        *span,
+                    )
+                    .into()),
+                }
+            }
+            // short-circuits: the right operand only runs when it decides the result
+            Expr::Logical {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let left = self.evaluate(left)?.is_truthy();
+                let result = match operator {
+                    LogicalOp::And => left && self.evaluate(right)?.is_truthy(),
+                    LogicalOp::Or => left || self.evaluate(right)?.is_truthy(),
+                };
+                Ok(Value::Bool(result))
+            }
+            Expr::Call { callee, args, span } => {
+                let callee_value = match callee.as_ref() {
+                    // changes to the receiver are written back to the variable holding it
+                    Expr::Get {
+                        object,
+                        name,
+                        span: member_span,
+                    } if methods::is_mutating(name) && object.is_assignment_target() => {
+                        return self.call_mutating_method(object, name, *member_span, args, *span);
+                    }
+                    Expr::Get {
+                        object,
+                        name,
+                        span: member_span,
+                    } => {
+                        let mut receiver = self.evaluate(object)?;
+                        if let Some(method) = methods::lookup(&receiver, name) {
+                            let values = self.method_arguments(method.name, args)?;
+                            return Ok(method.call(&mut receiver, values, *span)?);
+                        }
+                        member(receiver, name, *member_span)?
+                    }
+                    callee => self.evaluate(callee)?,
+                };
+                self.call_value(callee_value, callee.span(), args, *span)
+            }
+        }
+    }
+
+    // Calls the value of a callee, evaluating the arguments after it
+    fn call_value(
+        &mut self,
+        callee: Value,
+        callee_span: Span,
+        args: &[Argument],
+        span: Span,
+    ) -> Result<Value, Unwind> {
+        let function = match callee {
+            Value::Function(function) => function,
+            value => {
+                return Err(runtime_error(
+                    format!(
+                        "cannot call {}, only functions are callable",
+                        value.a_type_name()
+                    ),
+                    callee_span,
+                )
+                .into())
+            }
+        };
+
+        let mut values = Vec::new();
+        for arg in args {
+            values.push(self.evaluate(&arg.value)?);
+        }
+        let values = arrange_arguments(&function, args, values, span)?;
+        Ok(self.call_function(&function, values, span)?)
+    }
+}
+
+// The module made of the bindings a module body or file declared in `scope`
+fn module_value(name: &str, scope: &Environment) -> Value {
+    Value::Module(Rc::new(Module {
+        name: name.to_string(),
+        members: scope.local_values().into_iter().collect(),
+    }))
+}
+
+// Lexes and parses an imported file, keeping only the first problem found
+fn compile(source: &str) -> Result<Vec<Stmt>, Diagnostic> {
+    let tokens = lexer::new(source)
+        .parse()
+        .map_err(|mut diagnostics| diagnostics.remove(0))?;
+    parser::new(tokens)
+        .parse()
+        .map_err(|errors| errors[0].to_diagnostic(source))
+}
+
+// The items a `for` loop visits: the numbers of a range or the characters of a string
+// along with what `for (item, second) in ...` binds to its second name: the
+// position of the item, or for a map the value of the key
+fn iterate(
+    value: Value,
+    span: Span,
+) -> Result<Box<dyn Iterator<Item = (Value, Value)>>, RuntimeError> {
+    let items: Box<dyn Iterator<Item = Value>> = match value {
+        Value::Range(start, end) => Box::new((start..end).map(Value::Int)),
+        Value::String(s) => {
+            let chars: Vec<Value> = s.chars().map(|c| Value::String(c.to_string())).collect();
+            Box::new(chars.into_iter())
+        }
+        Value::List(items) => Box::new(Rc::unwrap_or_clone(items).into_iter()),
+        // the keys and their values, in insertion order
+        Value::Map(map) => {
+            let entries: Vec<(Value, Value)> = map
+                .iter()
+                .map(|(key, value)| (Value::String(key.clone()), value.clone()))
+                .collect();
+            return Ok(Box::new(entries.into_iter()));
+        }
+        value => {
+            return Err(runtime_error(
+                format!("cannot iterate over {}", value.a_type_name()),
+                span,
+            ))
+        }
+    };
+    let positions = (0..).map(Value::Int);
+    Ok(Box::new(items.zip(positions)))
+}
+
+// `object[index]` of a list, a string or a map, missing map keys are none
+fn element(object: Value, index: &Value, span: Span) -> Result<Value, RuntimeError> {
+    match object {
+        Value::Map(map) => Ok(map
+            .get(map_key(index, span)?)
+            .cloned()
+            .unwrap_or(Value::None)),
+        Value::List(items) => Ok(items[position(index, items.len(), span)?].clone()),
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(Value::String(
+                chars[position(index, chars.len(), span)?].to_string(),
+            ))
+        }
+        object => Err(runtime_error(
+            format!("cannot index into {}", object.a_type_name()),
+            span,
+        )),
+    }
+}
+
+// `object.name` of a map or a module
+fn member(object: Value, name: &str, span: Span) -> Result<Value, RuntimeError> {
+    match object {
+        Value::Map(map) => Ok(map.get(name).cloned().unwrap_or(Value::None)),
+        Value::Module(module) => match module.members.get(name) {
+            Some(Value::Function(function)) if !function.declaration.public => Err(runtime_error(
+                format!("'{}' is private to module '{}'", name, module.name),
+                span,
+            )),
+            Some(value) => Ok(value.clone()),
+            None => Err(runtime_error(
+                format!("module '{}' has no member '{}'", module.name, name),
+                span,
+            )),
+        },
+        value => Err(runtime_error(
+            format!("cannot access '{}' on {}", name, value.a_type_name()),
+            span,
+        )),
+    }
+}
+
+fn modify_error(err: AssignError, name: &str, span: Span) -> RuntimeError {
+    match err {
+        AssignError::Undefined => runtime_error(
+            format!("cannot assign to undeclared variable '{}'", name),
+            span,
+        ),
+        AssignError::Immutable => runtime_error(
+            format!(
+                "cannot modify '{}', it was declared with let (use var for mutable bindings)",
+                name
+            ),
+            span,
+        ),
+    }
+}
+
+// The element at the end of the `indices` path, e.g. `[(0, _), (1, _)]` for `xs[0][1]`
+fn element_mut<'v>(
+    target: &'v mut Value,
+    indices: &[(Value, Span)],
+) -> Result<&'v mut Value, RuntimeError> {
+    let Some(((index, span), rest)) = indices.split_first() else {
+        return Ok(target);
+    };
+    let element = match target {
+        Value::Map(map) => {
+            let key = map_key(index, *span)?;
+            match Rc::make_mut(map).get_mut(key) {
+                Some(entry) => entry,
+                None => return Err(runtime_error(format!("map has no key {:?}", key), *span)),
+            }
+        }
+        Value::List(items) => {
+            let position = position(index, items.len(), *span)?;
+            &mut Rc::make_mut(items)[position]
+        }
+        target => {
+            return Err(runtime_error(
+                format!("cannot assign to an element of {}", target.a_type_name()),
+                *span,
+            ))
+        }
+    };
+    element_mut(element, rest)
+}
+
+// The element at the end of the `indices` path, a missing map key reads as none
+fn get_element(target: &Value, indices: &[(Value, Span)]) -> Result<Value, RuntimeError> {
+    indices
+        .iter()
+        .try_fold(target.clone(), |value, (index, span)| {
+            element(value, index, *span)
+        })
+}
+
+// The receiver of a method call at the end of the `indices` path, where a
+// module member is reached by its name
+fn receiver_at(root: Value, indices: &[(Value, Span)]) -> Result<Value, RuntimeError> {
+    indices
+        .iter()
+        .try_fold(root, |value, (index, span)| match (value, index) {
+            (Value::Module(module), Value::String(name)) => {
+                member(Value::Module(module), name, *span)
+            }
+            (value, index) => element(value, index, *span),
+        })
+}
+
+// Sets the element at the end of the `indices` path
+fn set_element(
+    target: &mut Value,
+    indices: &[(Value, Span)],
+    value: Value,
+) -> Result<(), RuntimeError> {
+    let ((index, span), path) = indices.split_last().expect("an index to assign to");
+    match element_mut(target, path)? {
+        Value::Map(map) => {
+            let key = map_key(index, *span)?;
+            store(Rc::make_mut(map), key.to_string(), value);
+            Ok(())
+        }
+        Value::List(items) => {
+            let position = position(index, items.len(), *span)?;
+            Rc::make_mut(items)[position] = value;
+            Ok(())
+        }
+        target => Err(runtime_error(
+            format!("cannot assign to an element of {}", target.a_type_name()),
+            *span,
+        )),
+    }
+}
+
+// Assigning none to a map entry removes the key instead, `m.k = none` deletes `k`
+fn store(map: &mut Map, key: String, value: Value) {
+    match value {
+        Value::None => {
+            map.remove(&key);
+        }
+        value => map.insert(key, value),
+    }
+}
+
+fn map_key(key: &Value, span: Span) -> Result<&str, RuntimeError> {
+    match key {
+        Value::String(key) => Ok(key),
+        key => Err(runtime_error(
+            format!("map keys must be strings, found {}", key.type_name()),
+            span,
+        )),
+    }
+}
+
+// `object[start:end]`, bounds past either end are clamped like in most languages with slices
+fn slice(
+    object: Value,
+    start: Option<Value>,
+    end: Option<Value>,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let bounds = |len: usize| -> Result<(usize, usize), RuntimeError> {
+        let bound = |value: Option<Value>, default: usize| match value {
+            None => Ok(default),
+            Some(value) => match as_int(&value) {
+                Some(n) if n < 0 => Ok((len as i64 + n).max(0) as usize),
+                Some(n) => Ok((n as usize).min(len)),
+                None => Err(runtime_error(
+                    format!("slice bounds must be ints, found {}", value),
+                    span,
+                )),
+            },
+        };
+        let start = bound(start, 0)?;
+        Ok((start, bound(end, len)?.max(start)))
+    };
+
+    match object {
+        Value::List(items) => {
+            let (start, end) = bounds(items.len())?;
+            Ok(Value::List(Rc::new(items[start..end].to_vec())))
+        }
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (start, end) = bounds(chars.len())?;
+            Ok(Value::String(chars[start..end].iter().collect()))
+        }
+        object => Err(runtime_error(
+            format!("cannot slice {}", object.a_type_name()),
+            span,
+        )),
+    }
+}
+
+// Position of `index` in a sequence of `len` items, negative indices count from the end
+pub fn position(index: &Value, len: usize, span: Span) -> Result<usize, RuntimeError> {
+    let n = match as_int(index) {
+        Some(n) => n,
+        None => {
+            return Err(runtime_error(
+                format!("index must be an int, found {}", index),
+                span,
+            ))
+        }
+    };
+    let position = if n < 0 { n + len as i64 } else { n };
+    if position < 0 || position >= len as i64 {
+        return Err(runtime_error(
+            format!("index {} is out of bounds for length {}", n, len),
+            span,
+        ));
+    }
+    Ok(position as usize)
+}
+
+fn as_int(value: &Value) -> Option<i64> {
+    match value {
+        Value::Int(n) => Some(*n),
+        _ => None,
+    }
+}
+
+// Puts the evaluated `values` of `args` in parameter order, matching named
+// arguments to the parameter with the same name
+fn arrange_arguments(
+    function: &Function,
+    args: &[Argument],
+    values: Vec<Value>,
+    span: Span,
+) -> Result<Vec<Value>, RuntimeError> {
+    if args.len() != function.arity() {
+        return Err(arity_error(function, args.len(), span));
+    }
+
+    let params = &function.declaration.params;
+    let mut slots: Vec<Option<Value>> = vec![None; params.len()];
+    for (position, (arg, value)) in args.iter().zip(values).enumerate() {
+        let index = match &arg.name {
+            None => position,
+            Some(name) => match params.iter().position(|param| &param.name == name) {
+                Some(index) => index,
+                None => {
+                    return Err(runtime_error(
+                        format!("'{}' has no parameter named '{}'", function.name(), name),
+                        arg.span,
+                    ))
+                }
+            },
+        };
+
+        if slots[index].is_some() {
+            return Err(runtime_error(
+                format!(
+                    "argument '{}' of '{}' is given more than once",
+                    params[index].name,
+                    function.name()
+                ),
+                arg.span,
+            ));
+        }
+        slots[index] = Some(value);
+    }
+
+    // every slot is filled: there are as many arguments as parameters and none repeats
+    Ok(slots.into_iter().flatten().collect())
+}
+
+fn arity_error(function: &Function, found: usize, span: Span) -> RuntimeError {
+    runtime_error(
+        format!(
+            "'{}' expects {} argument(s), found {}",
+            function.name(),
+            function.arity(),
+            found
+        ),
+        span,
+    )
+}
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::Int(n) => Value::Int(*n),
+        Literal::Float(n) => Value::Float(*n),
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Bool(b) => Value::Bool(*b),
+        Literal::None => Value::None,
+    }
+}
+
+fn unary(operator: UnaryOp, operand: Value, span: Span) -> Result<Value, RuntimeError> {
+    match (operator, operand) {
+        (UnaryOp::Negate, Value::Int(n)) => match n.checked_neg() {
+            Some(n) => Ok(Value::Int(n)),
+            None => Err(overflow_error(token::MINUS, span)),
+        },
+        (UnaryOp::Negate, Value::Float(n)) => Ok(Value::Float(-n)),
+        (UnaryOp::Not, v) => Ok(Value::Bool(!v.is_truthy())),
+        (UnaryOp::Negate, v) => Err(runtime_error(
+            format!("operand of '-' must be a number, found {}", v.type_name()),
+            span,
+        )),
+    }
+}
+
+fn binary(
+    operator: BinaryOp,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match operator {
+        BinaryOp::Equal => Ok(Value::Bool(left == right)),
+        BinaryOp::NotEqual => Ok(Value::Bool(left != right)),
+        BinaryOp::Add => match (left, right) {
+            (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
+            (l, r) if is_number(&l) && is_number(&r) => {
+                arithmetic(operator, number_operands(operator, l, r, span)?, span)
+            }
+            (l, r) => Err(runtime_error(
+                format!(
+                    "operands of '+' must be two numbers or two strings, found {} and {}",
+                    l.type_name(),
+                    r.type_name()
+                ),
+                span,
+            )),
+        },
+        BinaryOp::Subtract
+        | BinaryOp::Multiply
+        | BinaryOp::Divide
+        | BinaryOp::Modulo
+        | BinaryOp::IntDivide
+        | BinaryOp::Power => {
+            let operands = number_operands(operator, left, right, span)?;
+            arithmetic(operator, operands, span)
+        }
+        BinaryOp::Greater | BinaryOp::GreaterEqual | BinaryOp::Less | BinaryOp::LessEqual => {
+            compare(operator, left, right, span)
+        }
+    }
+}
+
+fn is_number(value: &Value) -> bool {
+    matches!(value, Value::Int(_) | Value::Float(_))
+}
+
+// The operands of an arithmetic operator, ints are widened to floats next to a float
+enum Operands {
+    Ints(i64, i64),
+    Floats(f64, f64),
+}
+
+fn number_operands(
+    operator: BinaryOp,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Operands, RuntimeError> {
+    match (left, right) {
+        (Value::Int(l), Value::Int(r)) => Ok(Operands::Ints(l, r)),
+        (Value::Int(l), Value::Float(r)) => Ok(Operands::Floats(l as f64, r)),
+        (Value::Float(l), Value::Int(r)) => Ok(Operands::Floats(l, r as f64)),
+        (Value::Float(l), Value::Float(r)) => Ok(Operands::Floats(l, r)),
+        (l, r) => Err(runtime_error(
+            format!(
+                "operands of '{}' must be numbers, found {} and {}",
+                operator.as_str(),
+                l.type_name(),
+                r.type_name()
+            ),
+            span,
+        )),
+    }
+}
+
+fn arithmetic(operator: BinaryOp, operands: Operands, span: Span) -> Result<Value, RuntimeError> {
+    match operands {
+        Operands::Ints(l, r) => int_arithmetic(operator, l, r, span),
+        Operands::Floats(l, r) => float_arithmetic(operator, l, r, span),
+    }
+}
+
+// An int result that does not fit in 64 bits is an error rather than wrapping around
+fn int_arithmetic(
+    operator: BinaryOp,
+    left: i64,
+    right: i64,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let result = match operator {
+        BinaryOp::Divide | BinaryOp::Modulo | BinaryOp::IntDivide if right == 0 => {
+            return Err(runtime_error("division by zero".to_string(), span))
+        }
+        BinaryOp::Add => left.checked_add(right),
+        BinaryOp::Subtract => left.checked_sub(right),
+        BinaryOp::Multiply => left.checked_mul(right),
+        // `/` always gives a float, `~/` is the int division
+        BinaryOp::Divide => return Ok(Value::Float(left as f64 / right as f64)),
+        // the remainder takes the sign of the left operand, it always fits in an
+        // int, even for the smallest int divided by -1
+        BinaryOp::Modulo => Some(left.wrapping_rem(right)),
+        // rounds towards negative infinity
+        BinaryOp::IntDivide => left.checked_div(right).map(|quotient| {
+            if left % right != 0 && (left < 0) != (right < 0) {
+                quotient - 1
+            } else {
+                quotient
+            }
+        }),
+        BinaryOp::Power if right < 0 => return Ok(Value::Float((left as f64).powf(right as f64))),
+        _ => u32::try_from(right)
+            .ok()
+            .and_then(|right| left.checked_pow(right)),
+    };
+    match result {
+        Some(n) => Ok(Value::Int(n)),
+        None => Err(overflow_error(operator.as_str(), span)),
+    }
+}
+
+fn float_arithmetic(
+    operator: BinaryOp,
+    left: f64,
+    right: f64,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    match operator {
+        BinaryOp::Add => Ok(Value::Float(left + right)),
+        BinaryOp::Subtract => Ok(Value::Float(left - right)),
+        BinaryOp::Multiply => Ok(Value::Float(left * right)),
+        BinaryOp::Power => Ok(Value::Float(left.powf(right))),
+        BinaryOp::Divide | BinaryOp::Modulo | BinaryOp::IntDivide if right == 0.0 => {
+            Err(runtime_error("division by zero".to_string(), span))
+        }
+        BinaryOp::Modulo => Ok(Value::Float(left % right)),
+        // like for ints, the rounded down quotient is an int
+        BinaryOp::IntDivide => {
+            let quotient = (left / right).floor();
+            if quotient.is_finite() && quotient.abs() < i64::MAX as f64 {
+                Ok(Value::Int(quotient as i64))
+            } else {
+                Err(overflow_error(operator.as_str(), span))
+            }
+        }
+        _ => Ok(Value::Float(left / right)),
+    }
+}
+
+fn overflow_error(operator: &str, span: Span) -> RuntimeError {
+    runtime_error(
+        format!(
+            "integer overflow in '{}', the result does not fit in an int",
+            operator
+        ),
+        span,
+    )
+}
+
+fn compare(
+    operator: BinaryOp,
+    left: Value,
+    right: Value,
+    span: Span,
+) -> Result<Value, RuntimeError> {
+    let ordering = match (&left, &right) {
+        (Value::Int(l), Value::Int(r)) => Some(l.cmp(r)),
+        (Value::String(l), Value::String(r)) => Some(l.cmp(r)),
+        (Value::Int(l), Value::Float(r)) => (*l as f64).partial_cmp(r),
+        (Value::Float(l), Value::Int(r)) => l.partial_cmp(&(*r as f64)),
+        (Value::Float(l), Value::Float(r)) => l.partial_cmp(r),
+        _ => {
+            return Err(runtime_error(
+                format!(
+                    "cannot compare {} with {} using '{}'",
+                    left.type_name(),
+                    right.type_name(),
+                    operator.as_str()
+                ),
+                span,
+            ))
+        }
+    };
+
+    let result = match ordering {
+        Some(o) => match operator {
+            BinaryOp::Greater => o.is_gt(),
+            BinaryOp::GreaterEqual => o.is_ge(),
+            BinaryOp::Less => o.is_lt(),
+            _ => o.is_le(),
+        },
+        // NaN never compares
+        None => false,
+    };
+    Ok(Value::Bool(result))
+}
+
+pub fn new() -> Interpreter {
+    Interpreter {
+        environment: Rc::new(RefCell::new(environment::new())),
+        file: None,
+        search_path: Vec::new(),
+        modules: HashMap::new(),
+        importing: Vec::new(),
+        depth: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    fn eval(input: &str) -> Result<Value, RuntimeError> {
+        let mut lexer = lexer::new(input);
+        let tokens = lexer.parse().unwrap();
+        let mut parser = parser::new(tokens);
+        let statements = parser.parse().unwrap();
+        new().interpret(&statements)
+    }
+
+    #[test]
+    fn arithmetic_test() {
+        assert_eq!(Ok(Value::Int(7)), eval("1 + 2 * 3"));
+        assert_eq!(Ok(Value::Int(9)), eval("(1 + 2) * 3"));
+        assert_eq!(Ok(Value::Int(-4)), eval("-(6 - 2)"));
+        assert_eq!(Ok(Value::Float(2.5)), eval("5 / 2"));
+        assert_eq!(Ok(Value::Int(1)), eval("10 - 6 - 3"));
+
+        assert_eq!(Ok(Value::Int(1)), eval("7 % 3"));
+        assert_eq!(Ok(Value::Int(-1)), eval("-7 % 3"));
+        assert_eq!(Ok(Value::Int(3)), eval("7 ~/ 2"));
+        assert_eq!(Ok(Value::Int(-4)), eval("-7 ~/ 2"));
+        assert_eq!(Ok(Value::Int(512)), eval("2 ** 3 ** 2"));
+        assert_eq!(Ok(Value::Int(-4)), eval("-2 ** 2"));
+        assert_eq!(Ok(Value::Float(0.5)), eval("2 ** -1"));
+
+        // `/=` makes a float, which `%=` keeps
+        let result = eval("var x = 10\nx += 5\nx -= 3\nx *= 2\nx /= 4\nx %= 4\nx");
+        assert!(
+            matches!(result, Ok(Value::Float(n)) if n == 2.0),
+            "{:?}",
+            result
+        );
+        let result = eval("var x = 10\nx += 5\nx -= 3\nx *= 2\nx %= 5\nx");
+        assert!(matches!(result, Ok(Value::Int(4))), "{:?}", result);
+        assert_eq!(
+            Ok(Value::String("ab".to_string())),
+            eval("var s = \"a\"\ns += \"b\"\ns")
+        );
+
+        let err = eval("let x = 1\nx += 1").unwrap_err();
+        assert!(err.message.starts_with("cannot reassign 'x'"));
+        let err = eval("5 % 0").unwrap_err();
+        assert_eq!("division by zero", err.message);
+        let err = eval("5 ~/ 0").unwrap_err();
+        assert_eq!("division by zero", err.message);
+    }
+
+    #[test]
+    fn number_test() {
+        assert_eq!(Ok(Value::Float(3.5)), eval("7 / 2"));
+        assert_eq!(Ok(Value::Float(4.0)), eval("1.5 + 2.5"));
+        assert_eq!(Ok(Value::Float(3.5)), eval("1 + 2.5"));
+        assert_eq!(Ok(Value::Int(-4)), eval("-7.5 ~/ 2"));
+        assert_eq!(Ok(Value::Int(255)), eval("0xff"));
+        assert_eq!(Ok(Value::Int(97)), eval("b'a'"));
+        assert_eq!(Ok(Value::Float(1e9)), eval("1e9"));
+        assert_eq!(Ok(Value::Bool(true)), eval("1 == 1.0"));
+        assert_eq!(Ok(Value::Bool(true)), eval("1 < 1.5"));
+        assert_eq!("2.0", Value::Float(2.0).to_string());
+        assert_eq!("2", Value::Int(2).to_string());
+
+        let err = eval("9223372036854775807 + 1").unwrap_err();
+        assert_eq!(
+            "integer overflow in '+', the result does not fit in an int",
+            err.message
+        );
+        assert_eq!(Span::new(0, 23), err.span);
+        assert!(eval("2 ** 64").is_err());
+        assert!(eval("-(0 - 9223372036854775807 - 1)").is_err());
+        let min = "let m = 0 - 9223372036854775807 - 1\n";
+        assert_eq!(Ok(Value::Int(0)), eval(&format!("{}m % -1", min)));
+        assert!(eval(&format!("{}m ~/ -1", min)).is_err());
+    }
+
+    #[test]
+    fn comparison_test() {
+        assert_eq!(Ok(Value::Bool(true)), eval("1 < 2"));
+        assert_eq!(Ok(Value::Bool(false)), eval("3 >= 4"));
+        assert_eq!(Ok(Value::Bool(true)), eval("1 + 1 == 2"));
+        assert_eq!(Ok(Value::Bool(true)), eval("\"a\" != \"b\""));
+        assert_eq!(Ok(Value::Bool(false)), eval("1 == \"1\""));
+        assert_eq!(Ok(Value::Bool(true)), eval("none == none"));
+    }
+
+    #[test]
+    fn logical_test() {
+        assert_eq!(Ok(Value::Bool(true)), eval("1 == 1 && 2 == 2"));
+        assert_eq!(Ok(Value::Bool(false)), eval("1 == 1 && 2 == 3"));
+        assert_eq!(Ok(Value::Bool(true)), eval("false || 0"));
+        assert_eq!(Ok(Value::Bool(false)), eval("none || false"));
+        assert_eq!(Ok(Value::Bool(true)), eval("!none"));
+        assert_eq!(Ok(Value::Bool(false)), eval("!\"\""));
+
+        // the right operand is skipped once the left one decides
+        assert_eq!(Ok(Value::Bool(false)), eval("false && undefined_name"));
+        assert_eq!(Ok(Value::Bool(true)), eval("true || 1 / 0"));
+        assert_eq!(
+            Ok(Value::Int(0)),
+            eval("var calls = 0\ndef touch() {\n  calls = calls + 1\n  true\n}\nfalse && touch()\ncalls")
+        );
+    }
+
+    #[test]
+    fn list_test() {
+        let list = |items: &[i64]| {
+            Ok(Value::List(Rc::new(
+                items.iter().map(|n| Value::Int(*n)).collect(),
+            )))
+        };
+        assert_eq!(list(&[1, 2, 3]), eval("[1, 2, 3,]"));
+        assert_eq!(Ok(Value::Int(3)), eval("let xs = [1, 2, 3]\nxs[-1]"));
+        assert_eq!(list(&[2, 3]), eval("let xs = [1, 2, 3]\nxs[1:]"));
+        assert_eq!(list(&[1, 2]), eval("let xs = [1, 2, 3]\nxs[:-1]"));
+        assert_eq!(list(&[]), eval("[1, 2, 3][5:9]"));
+        assert_eq!(Ok(Value::String("ty".to_string())), eval("\"rusty\"[-2:]"));
+        assert_eq!(Ok(Value::Bool(true)), eval("[1, [2]] == [1, [2]]"));
+        assert_eq!(
+            "[1, \"a\", [none]]",
+            eval("[1, \"a\", [none]]").unwrap().to_string()
+        );
+
+        // lists are values, changing one copy leaves the others alone
+        assert_eq!(
+            Ok(Value::Int(13)),
+            eval("var grid = [[1, 2], [3, 4]]\nlet before = grid\ngrid[1][0] += 7\ngrid[1][0] + before[1][0]")
+        );
+        assert_eq!(
+            Ok(Value::Int(6)),
+            eval("var total = 0\nfor x in [1, 2, 3] {\n  total += x\n}\ntotal")
+        );
+
+        let err = eval("let xs = [1, 2]\nxs[5]").unwrap_err();
+        assert_eq!("index 5 is out of bounds for length 2", err.message);
+        assert_eq!(Span::new(19, 20), err.span);
+        let err = eval("var xs = [1, 2]\nxs[-3] = 0").unwrap_err();
+        assert_eq!("index -3 is out of bounds for length 2", err.message);
+        assert_eq!(Span::new(19, 21), err.span);
+        let err = eval("let xs = [1]\nxs[0] = 2").unwrap_err();
+        assert!(err.message.starts_with("cannot modify 'xs'"));
+        let err = eval("[1][0.5]").unwrap_err();
+        assert_eq!("index must be an int, found 0.5", err.message);
+    }
+
+    #[test]
+    fn compound_assignment_test() {
+        // the target's object and index are evaluated once, then read, combined and written
+        let counter = "var calls = 0\ndef next() {\n  calls += 1\n  calls - 1\n}\n";
+        assert_eq!(
+            "[11, 20, 30] 1",
+            eval(&format!(
+                "{}var xs = [10, 20, 30]\nxs[next()] += 1\n\"#{{xs}} #{{calls}}\"",
+                counter
+            ))
+            .unwrap()
+            .to_string()
+        );
+        assert_eq!(
+            "[[1, 6]] 2",
+            eval(&format!(
+                "{}var grid = [[1, 2]]\ngrid[next()][next()] *= 3\n\"#{{grid}} #{{calls}}\"",
+                counter
+            ))
+            .unwrap()
+            .to_string()
+        );
+        assert_eq!(Ok(Value::Int(3)), eval("var m = { n: 1 }\nm.n += 2"));
+        let err = eval("var m = {}\nm.n += 1").unwrap_err();
+        assert_eq!(
+            "operands of '+' must be two numbers or two strings, found none and int",
+            err.message
+        );
+    }
+
+    #[test]
+    fn map_test() {
+        let source = "var m = { \"b\": 1, a: 2 }\n";
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval(&format!("{}m[\"b\"] + m.a", source))
+        );
+        assert_eq!(Ok(Value::None), eval(&format!("{}m.missing", source)));
+
+        // new keys go last, existing ones keep their place, none removes a key
+        assert_eq!(
+            "{\"a\": 5, \"c\": [1]}",
+            eval(&format!("{}m.c = [1]\nm[\"a\"] = 5\nm.b = none\nm", source))
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            Ok(Value::String("ba".to_string())),
+            eval(&format!(
+                "{}var keys = \"\"\nfor k in m {{\n  keys += k\n}}\nkeys",
+                source
+            ))
+        );
+        assert_eq!(
+            Ok(Value::Int(4)),
+            eval("var m = { xs: [1, 2] }\nm.xs[1] += 2\nm[\"xs\"][-1]")
+        );
+        assert_eq!(
+            Ok(Value::Bool(true)),
+            eval("{ a: 1, b: 2 } == { b: 2, a: 1 }")
+        );
+        // only assignment removes keys, literals keep their none entries
+        assert_eq!(
+            "{\"a\": none, \"b\": 1}",
+            eval("let m = { a: none, b: 1 }\nm").unwrap().to_string()
+        );
+        assert_eq!(Ok(Value::Int(2)), eval("{ a: none, b: 1 }.len()"));
+
+        let err = eval("let m = { a: 1 }\nm.a = 2").unwrap_err();
+        assert!(err.message.starts_with("cannot modify 'm'"));
+        let err = eval("let m = { a: 1 }\nm[1]").unwrap_err();
+        assert_eq!("map keys must be strings, found int", err.message);
+        assert_eq!(Span::new(19, 20), err.span);
+        let err = eval("var m = {}\nm.a.b = 1").unwrap_err();
+        assert_eq!("map has no key \"a\"", err.message);
+
+        // maps have no `push`, so an entry of that name is called
+        let source = "def add(n) {\n  n + 1\n}\nlet m = { push: add, xs: { push: add } }\n";
+        assert_eq!(Ok(Value::Int(2)), eval(&format!("{}m.push(1)", source)));
+        assert_eq!(Ok(Value::Int(3)), eval(&format!("{}m.xs.push(2)", source)));
+        let err = eval("let m = { push: 1 }\nm.push(1)").unwrap_err();
+        assert_eq!(
+            "cannot call an int, only functions are callable",
+            err.message
+        );
+    }
+
+    #[test]
+    fn method_test() {
+        assert_eq!(Ok(Value::Int(3)), eval("\"abc\".len()"));
+        assert_eq!(
+            Ok(Value::String("ABC".to_string())),
+            eval("\" abc \".trim().upper()")
+        );
+        assert_eq!(
+            Ok(Value::String("a-b-c".to_string())),
+            eval("\"a,b,c\".split(\",\").join(\"-\")")
+        );
+        assert_eq!(Ok(Value::Bool(true)), eval("[1, 2].contains(2)"));
+        assert_eq!(
+            "[\"b\", \"a\"]",
+            eval("{ b: 1, a: 2 }.keys()").unwrap().to_string()
+        );
+
+        // changes are written back to the variable, or the element, holding the receiver
+        assert_eq!(
+            "[0, 1, 2, 4]",
+            eval("var xs = [1, 2, 3]\nxs.push(4)\nxs.remove(-2)\nxs.insert(0, 0)\nxs")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            "{\"a\": [1, 2]}",
+            eval("var m = { a: [1], b: 2 }\nm.a.push(2)\nm.remove(\"b\")\nm")
+                .unwrap()
+                .to_string()
+        );
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval("var grid = [[1, 2, 3]]\ngrid[0].pop()")
+        );
+        // a map entry holding a function is called like a method
+        assert_eq!(
+            Ok(Value::Int(2)),
+            eval("def twice(n) {\n  n * 2\n}\nlet m = { f: twice }\nm.f(1)")
+        );
+
+        let err = eval("let xs = [1]\nxs.push(2)").unwrap_err();
+        assert!(err.message.starts_with("cannot modify 'xs'"));
+        assert_eq!(Span::new(13, 15), err.span);
+        let err = eval("var xs = []\nxs.pop()").unwrap_err();
+        assert_eq!("cannot pop from an empty list", err.message);
+        let err = eval("var n = 1\nn.push(2)").unwrap_err();
+        assert_eq!("int has no method 'push'", err.message);
+        let err = eval("\"abc\".len(1)").unwrap_err();
+        assert_eq!("'len' expects 0 argument(s), found 1", err.message);
+        let err = eval("\"abc\".size()").unwrap_err();
+        assert_eq!("cannot access 'size' on a string", err.message);
+    }
+
+    #[test]
+    fn interpolation_test() {
+        assert_eq!(
+            Ok(Value::String(
+                "total: 3.5 of [1, \"a\"] in {\"k\": 8}".to_string()
+            )),
+            eval("let a = 1\nlet xs = [1, \"a\"]\n\"total: #{a + 2.5} of #{xs} in #{ { k: 2 ** 3 } }\"")
+        );
+        assert_eq!(
+            Ok(Value::String("a-b".to_string())),
+            eval("let m = { \"k\": \"b\" }\n\"#{\"a\"}-#{m[\"k\"]}\"")
+        );
+    }
+
+    #[test]
+    fn string_test() {
+        assert_eq!(
+            Ok(Value::String("hello rusty".to_string())),
+            eval("\"hello \" + \"rusty\"")
+        );
+    }
+
+    #[test]
+    fn variables_test() {
+        assert_eq!(Ok(Value::Int(3)), eval("let x = 1\nlet y = 2\nx + y"));
+        assert_eq!(Ok(Value::Int(10)), eval("var x = 2\nx = 5 + 5\nx"));
+        assert_eq!(Ok(Value::None), eval("var x\nx"));
+
+        // blocks open a new scope, assignments reach the enclosing one
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval("var x = 1\nvar y = 0\n{\n  let x = 2\n  y = x + 1\n}\nx * y")
+        );
+        assert_eq!(Ok(Value::Int(1)), eval("let x = 1\n{ let x = 2 }\nx"));
+
+        let err = eval("let x = 1\nx = 2").unwrap_err();
+        assert!(err.message.starts_with("cannot reassign 'x'"));
+
+        let err = eval("y = 2").unwrap_err();
+        assert_eq!("cannot assign to undeclared variable 'y'", err.message);
+
+        let err = eval("{ let z = 1 }\nz").unwrap_err();
+        assert_eq!("undefined variable 'z'", err.message);
+    }
+
+    #[test]
+    fn function_test() {
+        let source = "let base = 10
+def add_two(a: int, b: int): int {
+    a + b + base
+}
+def first(a) {
+    return a
+    a + 1
+}
+def nothing { return }
+def adder(n) {
+    def add(x) { x + n }
+    add
+}
+";
+        let run = |code: &str| eval(&format!("{}{}", source, code));
+
+        assert_eq!(Ok(Value::Int(13)), run("add_two(1, 2)"));
+        assert_eq!(Ok(Value::Int(13)), run("add_two(b: 2, a: 1)"));
+        assert_eq!(Ok(Value::Int(13)), run("add_two(1, b: 2)"));
+        assert_eq!(Ok(Value::Int(1)), run("first(1)"));
+        assert_eq!(Ok(Value::None), run("nothing()"));
+        assert_eq!(Ok(Value::Int(5)), run("adder(2)(3)"));
+        // functions are values
+        assert_eq!(Ok(Value::Int(13)), run("let f = add_two\nf(1, 2)"));
+
+        let err = run("add_two(1)").unwrap_err();
+        assert_eq!("'add_two' expects 2 argument(s), found 1", err.message);
+        assert_eq!(Span::new(source.len(), source.len() + 10), err.span);
+
+        let err = run("add_two(1, c: 2)").unwrap_err();
+        assert_eq!("'add_two' has no parameter named 'c'", err.message);
+
+        let err = run("add_two(1, a: 2)").unwrap_err();
+        assert_eq!(
+            "argument 'a' of 'add_two' is given more than once",
+            err.message
+        );
+
+        let err = run("base(1)").unwrap_err();
+        assert_eq!(
+            "cannot call an int, only functions are callable",
+            err.message
+        );
+
+        // parameters are immutable and do not leak into the caller's scope
+        let err = run("def f(n) { n = 1 }\nf(2)").unwrap_err();
+        assert!(err.message.starts_with("cannot reassign 'n'"));
+        let err = run("first(1)\na").unwrap_err();
+        assert_eq!("undefined variable 'a'", err.message);
+    }
+
+    #[test]
+    fn if_test() {
+        assert_eq!(Ok(Value::Int(1)), eval("if 1 < 2 { 1 } else { 2 }"));
+        assert_eq!(Ok(Value::Int(2)), eval("if 1 > 2 { 1 } else { 2 }"));
+        assert_eq!(Ok(Value::None), eval("if false { 1 }"));
+        assert_eq!(
+            Ok(Value::String("b".to_string())),
+            eval("let n = 2\nif n == 1 { \"a\" } elsif n == 2 { \"b\" } else { \"c\" }")
+        );
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval("var x = 1\nif (x == 1), { x = 3 }\nx")
+        );
+
+        // only false and none are falsy
+        assert_eq!(Ok(Value::Bool(true)), eval("if 0 { true } else { false }"));
+        assert_eq!(
+            Ok(Value::Bool(true)),
+            eval("if \"\" { true } else { false }")
+        );
+        assert_eq!(
+            Ok(Value::Bool(false)),
+            eval("if none { true } else { false }")
+        );
+
+        // branches are scopes of their own
+        let err = eval("if true { let y = 1 }\ny").unwrap_err();
+        assert_eq!("undefined variable 'y'", err.message);
+    }
+
+    #[test]
+    fn case_test() {
+        let source = "def describe(n) {
+    case n {
+        1, 2: \"low\"
+        3: {
+            let word = \"three\"
+            word
+        }
+        \"x\": \"letter\"
+        none: \"other\"
+    }
+}
+";
+        let run = |code: &str| eval(&format!("{}{}", source, code));
+
+        assert_eq!(Ok(Value::String("low".to_string())), run("describe(2)"));
+        assert_eq!(Ok(Value::String("three".to_string())), run("describe(3)"));
+        assert_eq!(
+            Ok(Value::String("letter".to_string())),
+            run("describe(\"x\")")
+        );
+        assert_eq!(Ok(Value::String("other".to_string())), run("describe(4)"));
+        assert_eq!(
+            Ok(Value::String("other".to_string())),
+            run("describe(none)")
+        );
+
+        // without a fallback an unmatched case is none
+        assert_eq!(Ok(Value::None), eval("case 5 { 1: 1 }"));
+        assert_eq!(Ok(Value::Int(2)), eval("case -1 { 1: 1, -1: 2 }"));
+    }
+
+    #[test]
+    fn for_test() {
+        assert_eq!(
+            Ok(Value::Int(10)),
+            eval("var sum = 0\nfor i in 0..5 { sum = sum + i }\nsum")
+        );
+        assert_eq!(
+            Ok(Value::String("cba".to_string())),
+            eval("var out = \"\"\nfor c in \"abc\" { out = c + out }\nout")
+        );
+
+        // the index counts the visited items, starting at 0
+        assert_eq!(
+            Ok(Value::Int(6)),
+            eval("var sum = 0\nfor (n, i) in 10..14 { sum = sum + i }\nsum")
+        );
+
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval("var sum = 0\nfor i in 0..10 {\n  if i == 3 { break }\n  sum = sum + i\n}\nsum")
+        );
+        assert_eq!(
+            Ok(Value::Int(8)),
+            eval("var sum = 0\nfor i in 0..5 {\n  if i == 2 { continue }\n  sum = sum + i\n}\nsum")
+        );
+
+        // return leaves the loop and the function
+        assert_eq!(
+            Ok(Value::Int(3)),
+            eval("def find(s) {\n  for (c, i) in s { if c == \"d\" { return i } }\n}\nfind(\"abcd\")")
+        );
+
+        // maps give their keys, and the value as the second name
+        assert_eq!(
+            Ok(Value::String("a=1 b=2 ".to_string())),
+            eval("var out = \"\"\nfor (k, v) in { a: 1, b: 2 } { out += \"#{k}=#{v} \" }\nout")
+        );
+        assert_eq!(
+            Ok(Value::String("ab".to_string())),
+            eval("var out = \"\"\nfor k in { a: 1, b: 2 } { out += k }\nout")
+        );
+
+        let err = eval("for i in true { }").unwrap_err();
+        assert_eq!("cannot iterate over a bool", err.message);
+        assert_eq!(Span::new(9, 13), err.span);
+
+        let err = eval("for i in 0..1.5 { }").unwrap_err();
+        assert_eq!("range bounds must be ints, found 0 and 1.5", err.message);
+    }
+
+    #[test]
+    fn recursion_limit_test() {
+        // the default stack of a test thread is too small for MAX_CALL_DEPTH calls
+        std::thread::Builder::new()
+            .stack_size(512 * 1024 * 1024)
+            .spawn(|| {
+                let count = "def count(n) { if n == 0 { 0 } else { count(n - 1) + 1 } }\n";
+                assert_eq!(Ok(Value::Int(999)), eval(&format!("{}count(999)", count)));
+
+                let err = eval(&format!("{}count(5000)", count)).unwrap_err();
+                assert_eq!(RECURSION_ERROR, err.kind);
+                assert_eq!("calls nested more than 1000 deep in 'count'", err.message);
+                assert_eq!(MAX_CALL_DEPTH, err.trace.len());
+
+                // it can be handled like any other error, after which calls work again
+                assert_eq!(
+                    Ok(Value::Int(3)),
+                    eval(&format!(
+                        "{}handle {{ count(5000) }} error \"recursion\" {{ count(3) }}",
+                        count
+                    ))
+                );
+            })
+            .unwrap()
+            .join()
+            .unwrap();
+    }
+
+    #[test]
+    fn error_handling_test() {
+        let source = "def open(path) {
+    if path == \"\" { raise error(\"empty path\", path, kind: \"io\") }
+    path
+}
+def load(path) { open(path) }
+";
+        let run = |code: &str| eval(&format!("{}{}", source, code));
+
+        assert_eq!(
+            Ok(Value::String("a.rty".to_string())),
+            run("handle { load(\"a.rty\") } error { \"fallback\" }")
+        );
+        assert_eq!(
+            Ok(Value::String("fallback".to_string())),
+            run("handle { load(\"\") } error \"io\" { \"fallback\" }")
+        );
+
+        // the first handler with a matching kind runs
+        assert_eq!(
+            Ok(Value::String("empty path at open\nat load".to_string())),
+            run("handle { load(\"\") } error \"parse\" { 1 } error (message, data, trace) {\n  message + data + \" \" + trace\n}")
+        );
+
+        // errors raised by the interpreter can be handled as well
+        assert_eq!(
+            Ok(Value::String("division by zero".to_string())),
+            eval("handle { 1 / 0 } error \"runtime\" (message) { message }")
+        );
+
+        // errors are values until they are raised
+        let value = run("let e = error(\"oops\", kind: \"custom\")\ne").unwrap();
+        assert_eq!("<error custom: oops>", value.to_string());
+        assert_eq!(
+            Ok(Value::String("oops".to_string())),
+            eval("handle { raise \"oops\" } error e {\n  handle { raise e } error (message) { message }\n}")
+        );
+
+        let err = run("handle { load(\"\") } error \"parse\" { 1 }").unwrap_err();
+        assert_eq!("io: empty path", err.describe());
+        assert_eq!(
+            vec!["open", "load"],
+            err.trace
+                .iter()
+                .map(|f| f.function.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let err = eval("raise 1").unwrap_err();
+        assert_eq!("can only raise errors and strings, found int", err.message);
+        let err = eval("error(kind: \"io\")").unwrap_err();
+        assert_eq!("'error' needs a message", err.message);
+    }
+
+    #[test]
+    fn module_test() {
+        let source = "let base = 40
+module mymodule {
+    def public_function() { helper() + 1 }
+    defp helper() { base + 1 }
+    let version = 2
+}
+";
+        let run = |code: &str| eval(&format!("{}{}", source, code));
+
+        assert_eq!(Ok(Value::Int(42)), run("mymodule.public_function()"));
+        assert_eq!(Ok(Value::Int(2)), run("mymodule.version"));
+
+        let err = run("mymodule.helper()").unwrap_err();
+        assert_eq!("'helper' is private to module 'mymodule'", err.message);
+        let err = run("mymodule.missing").unwrap_err();
+        assert_eq!("module 'mymodule' has no member 'missing'", err.message);
+        let err = run("base.x").unwrap_err();
+        assert_eq!("cannot access 'x' on an int", err.message);
+
+        // module members are not visible unqualified
+        let err = run("helper()").unwrap_err();
+        assert_eq!("undefined variable 'helper'", err.message);
+
+        // a member named like a built-in method that changes its receiver
+        assert_eq!(
+            Ok(Value::Int(2)),
+            eval("module stack {\n    def push(a) { a + 1 }\n}\nstack.push(1)")
+        );
+    }
+
+    #[test]
+    fn import_test() {
+        let dir = std::env::temp_dir().join(format!("rusty_import_test_{}", std::process::id()));
+        fs::create_dir_all(dir.join("lib")).unwrap();
+        fs::write(
+            dir.join("util.rty"),
+            "import lib.strings\ndef greet(s) { strings.prefix + s }\ndefp secret() { 1 }\ndef fail() { 1 / 0 }\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("lib").join("strings.rty"),
+            "let prefix = \"hi \"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("a.rty"), "import b\n").unwrap();
+        fs::write(dir.join("b.rty"), "import a\n").unwrap();
+        fs::write(dir.join("broken.rty"), "let = 1\n").unwrap();
+        fs::write(
+            dir.join("mistyped.rty"),
+            "def f() { 1 }\nlet x = 1 - \"a\"\n",
+        )
+        .unwrap();
+
+        let run = |code: &str| {
+            let statements = parser::new(lexer::new(code).parse().unwrap())
+                .parse()
+                .unwrap();
+            let mut interpreter = new();
+            interpreter.set_search_path(vec![PathBuf::from("/nonexistent"), dir.clone()]);
+            interpreter.interpret(&statements)
+        };
+
+        assert_eq!(
+            Ok(Value::String("hi rusty".to_string())),
+            run("import util\nutil.greet(\"rusty\")")
+        );
+        // a module is loaded once, however often it is imported
+        assert_eq!(
+            Ok(Value::Bool(true)),
+            run("import util\nlet first = util\nimport util\nfirst == util")
+        );
+
+        let err = run("import util\nutil.secret()").unwrap_err();
+        assert_eq!("'secret' is private to module 'util'", err.message);
+
+        // errors keep track of the file they were raised in
+        let err = run("import util\nutil.fail()").unwrap_err();
+        assert_eq!(Some(dir.join("util.rty")), err.file.map(|f| f.path.clone()));
+        assert_eq!(None, err.trace[0].file);
+
+        let err = run("import a").unwrap_err();
+        assert_eq!("import cycle: a -> b -> a", err.message);
+        assert_eq!(
+            vec!["import b", "import a"],
+            err.trace
+                .iter()
+                .map(|f| f.function.as_str())
+                .collect::<Vec<_>>()
+        );
+
+        let err = run("import broken").unwrap_err();
+        assert_eq!(
+            "syntax error in module 'broken': expected variable name after 'let', found '='",
+            err.message
+        );
+
+        // a module that does not type check is not run
+        let err = run("import mistyped").unwrap_err();
+        assert_eq!(
+            "type error in module 'mistyped': operands of '-' must be numbers, found int and string",
+            err.message
+        );
+        assert_eq!(
+            Some(dir.join("mistyped.rty")),
+            err.file.map(|f| f.path.clone())
+        );
+        assert_eq!(Span::new(22, 29), err.span);
+
+        let err = run("import missing").unwrap_err();
+        assert!(err
+            .message
+            .starts_with("cannot find module 'missing': no missing.rty in"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn type_error_test() {
+        let err = eval("1 + \"a\"").unwrap_err();
+        assert!(err.message.contains("found int and string"));
+        assert_eq!(Span::new(0, 7), err.span);
+
+        let err = eval("-true").unwrap_err();
+        assert!(err.message.contains("must be a number"));
+
+        let err = eval("1 < \"a\"").unwrap_err();
+        assert!(err.message.contains("cannot compare"));
+
+        let err = eval("4 / 0").unwrap_err();
+        assert_eq!("division by zero", err.message);
+
+        let err = eval("x").unwrap_err();
+        assert_eq!("undefined variable 'x'", err.message);
+    }
+}
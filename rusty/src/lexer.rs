@@ -1,9 +1,9 @@
 #![allow(dead_code)]
 
-use crate::token::{self, Token};
+use crate::diagnostic::Diagnostic;
+use crate::token::{self, Span};
 use lazy_static::lazy_static;
 use std::collections::HashMap;
-use std::process;
 
 lazy_static! {
     static ref KEYWORDS: HashMap<&'static str, token::TokenType> = {
@@ -20,6 +20,8 @@ lazy_static! {
         map.insert(token::FOR, token::TokenType::For);
         map.insert(token::CASE, token::TokenType::Case);
         map.insert(token::CONTINUE, token::TokenType::Continue);
+        map.insert(token::BREAK, token::TokenType::Break);
+        map.insert(token::IN, token::TokenType::In);
         map.insert(token::NONE, token::TokenType::None);
         map.insert(token::TRUE, token::TokenType::True);
         map.insert(token::FALSE, token::TokenType::False);
@@ -28,156 +30,195 @@ lazy_static! {
         map.insert(token::ERROR, token::TokenType::Error);
         map.insert(token::HANDLE, token::TokenType::Handle);
         map.insert(token::RAISE, token::TokenType::Raise);
+        map.insert(token::RETURN, token::TokenType::Return);
+        map.insert(token::IMPORT, token::TokenType::Import);
 
         map
     };
 }
 
-pub struct Lexer {
-    input: String,
-    position: usize, // current position in input (points to current char)
-    line: usize,
-    read_position: usize, // read position to look ahead
+// An expression embedded in a string with `#{`, still being lexed
+struct Interpolation<'a> {
+    braces: usize, // '{' opened inside the expression and not closed yet
+    triple: bool,  // whether the string uses triple quotes
+    open: token::Token<'a>,
 }
 
-impl Lexer {
-    pub fn parse(&mut self) -> Vec<token::Token> {
-        println!("parsing the input: {}", self.input);
+// Tokens borrow their values from the input, so the input has to outlive them
+pub struct Lexer<'a> {
+    input: &'a str,
+    position: usize, // byte offset of the next char to read
+    line: usize,
+    column: usize, // column of the next char to read, counted in chars
+    start: usize,  // byte offset where the current token starts
+    start_line: usize,
+    start_column: usize,
+    interpolations: Vec<Interpolation<'a>>, // innermost last
+}
 
+impl<'a> Lexer<'a> {
+    // Turns the input into tokens. Lexing carries on past bad input so that every
+    // problem in the source is reported in one go.
+    pub fn parse(&mut self) -> Result<Vec<token::Token<'a>>, Vec<Diagnostic>> {
         let mut tokens = Vec::new();
+        let mut diagnostics = Vec::new();
         while self.has_more_token() {
             let token = self.next_token();
             if matches!(token.token_type, token::TokenType::Whitespace) {
                 continue;
             }
-            if matches!(token.token_type, token::TokenType::Comment) {
-                //for comment token we just consume the rest of the line
-                while !self.match_next_char('\n') {
-                    self.increment_position();
-                    continue;
-                }
+            if matches!(token.token_type, token::TokenType::UnterminatedComment) {
+                // pointing at the opening `/*`, the rest may run over many lines
+                let open = Span::new(token.span.start, token.span.start + 2);
+                diagnostics.push(
+                    Diagnostic::error(
+                        "unterminated block comment".to_string(),
+                        token.line,
+                        token.column,
+                        open,
+                    )
+                    .with_hint(
+                        "close the comment with '*/', comments opened inside it need one too",
+                    ),
+                );
+                continue;
             }
             if matches!(token.token_type, token::TokenType::Illegal) {
-                print_error(
-                    format!(
-                        "uncrecognized character '{}' at col {}, line {}",
-                        token.val, token.col, self.line
-                    )
-                    .as_str(),
+                diagnostics.push(
+                    self.diagnostic(&token, format!("unrecognized character '{}'", token.val))
+                        .with_hint("remove the character or put it inside a string"),
                 );
-                println!("^^^exiting program execution^^^");
-                process::exit(1);
+                continue;
             }
             if matches!(token.token_type, token::TokenType::UnterminatedString) {
-                print_error(
-                    format!(
-                        "Unterminated string '{}...' at col {}, line {}",
-                        token.val, token.col, self.line
-                    )
-                    .as_str(),
+                diagnostics.push(
+                    self.diagnostic(&token, "unterminated string".to_string())
+                        .with_hint(
+                            "add a closing '\"', or use \"\"\" for text spanning several lines",
+                        ),
                 );
-                println!("^^^exiting program execution^^^");
-                process::exit(1);
+                continue;
+            }
+            if matches!(token.token_type, token::TokenType::InvalidEscape) {
+                diagnostics.push(
+                    self.diagnostic(&token, format!("invalid escape sequence '{}'", token.val))
+                        .with_hint("valid escapes are \\n, \\t, \\r, \\\\, \\\", \\# and \\u{...}, or use a raw string r\"...\""),
+                );
+                continue;
             }
             if matches!(token.token_type, token::TokenType::InvalidNumber) {
-                print_error(
-                    format!(
-                        "Invalid number input '{}...' at col {}, line {}",
-                        token.val, token.col, self.line
-                    )
-                    .as_str(),
+                diagnostics.push(
+                    self.diagnostic(&token, format!("invalid number '{}'", token.val))
+                        .with_hint("'_' only goes between digits, and ints must fit in 64 bits"),
                 );
-                println!("^^^exiting program execution^^^");
-                process::exit(1);
+                continue;
+            }
+            if matches!(token.token_type, token::TokenType::InvalidByte) {
+                diagnostics.push(
+                    self.diagnostic(&token, format!("invalid byte literal {}", token.val))
+                        .with_hint("a byte is one ASCII char or an escape such as \\n or \\x7f"),
+                );
+                continue;
             }
             tokens.push(token);
         }
 
-        let eof_token = Token {
-            token_type: token::TokenType::EndOfFile,
-            val: token::EOF,
-            col: 0,
-        };
-        tokens.push(eof_token);
+        for interpolation in self.interpolations.drain(..) {
+            diagnostics.push(
+                Diagnostic::error(
+                    "unterminated string interpolation".to_string(),
+                    interpolation.open.line,
+                    interpolation.open.column,
+                    interpolation.open.span,
+                )
+                .with_hint("close the embedded expression with '}'"),
+            );
+        }
 
-        tokens
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+
+        self.start_token();
+        tokens.push(self.single_char_token(token::TokenType::EndOfFile));
+
+        Ok(tokens)
+    }
+
+    fn diagnostic(&self, token: &token::Token<'a>, message: String) -> Diagnostic {
+        Diagnostic::error(message, token.line, token.column, token.span)
     }
 
-    fn next_token(&mut self) -> token::Token {
+    fn next_token(&mut self) -> token::Token<'a> {
+        self.start_token();
         let next_char = self.read_char();
         match next_char {
             Some(c) => match c {
-                ' ' | '\r' | '\t' => return self.single_char_token(token::TokenType::Whitespace),
-                '\n' => {
-                    self.line = self.line + 1;
-                    return self.single_char_token(token::TokenType::Newline);
+                ' ' | '\r' | '\t' => self.single_char_token(token::TokenType::Whitespace),
+                '\n' => self.single_char_token(token::TokenType::Newline),
+                '\0' => self.single_char_token(token::TokenType::EndOfFile),
+                '+' => {
+                    self.multi_char_token('=', token::TokenType::PlusAssign, token::TokenType::Plus)
                 }
-                '\0' => return self.single_char_token(token::TokenType::EndOfFile),
-                '+' => return self.single_char_token(token::TokenType::Plus),
-                '-' => return self.single_char_token(token::TokenType::Minus),
-                '*' => return self.single_char_token(token::TokenType::Multiply),
-                '/' => {
-                    return self.multi_char_token(
-                        '/',
-                        token::TokenType::Comment,
-                        token::TokenType::Divide,
-                    )
-                }
-                '%' => return self.single_char_token(token::TokenType::Modulo),
-                '(' => return self.single_char_token(token::TokenType::LeftParen),
-                ')' => return self.single_char_token(token::TokenType::RightParen),
-                '{' => return self.single_char_token(token::TokenType::LeftBrace),
-                '}' => return self.single_char_token(token::TokenType::RightBrace),
-                '[' => return self.single_char_token(token::TokenType::LeftBracket),
-                ']' => return self.single_char_token(token::TokenType::RightBracket),
-                ',' => return self.single_char_token(token::TokenType::Comma),
-                ':' => return self.single_char_token(token::TokenType::Colon),
+                '-' => self.multi_char_token(
+                    '=',
+                    token::TokenType::MinusAssign,
+                    token::TokenType::Minus,
+                ),
+                '*' => self.one_of_tokens(
+                    &[
+                        ('*', token::TokenType::Power),
+                        ('=', token::TokenType::MultiplyAssign),
+                    ],
+                    token::TokenType::Multiply,
+                ),
+                '/' if self.match_next_char('/') => self.line_comment(),
+                '/' if self.match_next_char('*') => self.block_comment(),
+                '/' => self.multi_char_token(
+                    '=',
+                    token::TokenType::DivideAssign,
+                    token::TokenType::Divide,
+                ),
+                '%' => self.multi_char_token(
+                    '=',
+                    token::TokenType::ModuloAssign,
+                    token::TokenType::Modulo,
+                ),
+                '~' => self.multi_char_token(
+                    '/',
+                    token::TokenType::IntDivide,
+                    token::TokenType::Illegal,
+                ),
+                '(' => self.single_char_token(token::TokenType::LeftParen),
+                ')' => self.single_char_token(token::TokenType::RightParen),
+                '{' | '}' => self.brace_token(c),
+                '[' => self.single_char_token(token::TokenType::LeftBracket),
+                ']' => self.single_char_token(token::TokenType::RightBracket),
+                ',' => self.single_char_token(token::TokenType::Comma),
+                ':' => self.single_char_token(token::TokenType::Colon),
+                '.' => self.multi_char_token('.', token::TokenType::DotDot, token::TokenType::Dot),
                 '!' => {
-                    return self.multi_char_token(
-                        '=',
-                        token::TokenType::NotEqual,
-                        token::TokenType::Illegal,
-                    )
+                    self.multi_char_token('=', token::TokenType::NotEqual, token::TokenType::Not)
                 }
                 '=' => {
-                    return self.multi_char_token(
-                        '=',
-                        token::TokenType::Equal,
-                        token::TokenType::Assign,
-                    )
-                }
-                '>' => {
-                    return self.multi_char_token(
-                        '=',
-                        token::TokenType::GreaterThanOrEqual,
-                        token::TokenType::GreaterThan,
-                    )
-                }
-                '<' => {
-                    return self.multi_char_token(
-                        '=',
-                        token::TokenType::LesserThanOrEqual,
-                        token::TokenType::LesserThan,
-                    )
-                }
-                '&' => {
-                    return self.multi_char_token(
-                        '&',
-                        token::TokenType::And,
-                        token::TokenType::Illegal,
-                    )
+                    self.multi_char_token('=', token::TokenType::Equal, token::TokenType::Assign)
                 }
-                '|' => {
-                    return self.multi_char_token(
-                        '|',
-                        token::TokenType::Or,
-                        token::TokenType::Illegal,
-                    )
-                }
-                '"' => return self.get_string_token(),
-                _ => return self.get_complex_token(c),
+                '>' => self.multi_char_token(
+                    '=',
+                    token::TokenType::GreaterThanOrEqual,
+                    token::TokenType::GreaterThan,
+                ),
+                '<' => self.multi_char_token(
+                    '=',
+                    token::TokenType::LesserThanOrEqual,
+                    token::TokenType::LesserThan,
+                ),
+                '&' => self.multi_char_token('&', token::TokenType::And, token::TokenType::Illegal),
+                '|' => self.multi_char_token('|', token::TokenType::Or, token::TokenType::Illegal),
+                '"' => self.get_string_token(false),
+                _ => self.get_complex_token(c),
             },
-            None => return self.single_char_token(token::TokenType::Illegal),
+            None => self.single_char_token(token::TokenType::Illegal),
         }
     }
 
@@ -186,142 +227,396 @@ impl Lexer {
         expected_char: char,
         expected_token: token::TokenType,
         default_token: token::TokenType,
-    ) -> token::Token {
+    ) -> token::Token<'a> {
         if self.match_next_char(expected_char) {
-            self.increment_position();
+            self.read_char();
             self.single_char_token(expected_token)
+        } else if default_token == token::TokenType::Illegal {
+            self.token_from_source(default_token)
         } else {
             self.single_char_token(default_token)
         }
     }
 
-    fn get_string_token(&mut self) -> token::Token {
-        let position = self.position;
-        while !self.match_next_char('"') && self.has_more_token() {
-            self.increment_position();
+    // like multi_char_token, for operators with several two char forms, e.g. `*`, `**` and `*=`
+    fn one_of_tokens(
+        &mut self,
+        alternatives: &[(char, token::TokenType)],
+        default_token: token::TokenType,
+    ) -> token::Token<'a> {
+        for (expected_char, token_type) in alternatives {
+            if self.match_next_char(*expected_char) {
+                self.read_char();
+                return self.single_char_token(*token_type);
+            }
+        }
+        self.single_char_token(default_token)
+    }
+
+    // `// ...` up to the end of the line or of the input, `/// ...` documents what follows
+    fn line_comment(&mut self) -> token::Token<'a> {
+        while !matches!(self.peek_char(), '\n' | '\0') {
+            self.read_char();
         }
+        // `////` and longer are plain comments, e.g. separator lines
+        let text = &self.input[self.start..self.position];
+        let token_type = if text.starts_with(token::DOC_COMMENT) && !text.starts_with("////") {
+            token::TokenType::DocComment
+        } else {
+            token::TokenType::Comment
+        };
+        self.token_from_source(token_type)
+    }
 
-        if !self.has_more_token() {
-            let mut end_position = self.position;
-            if end_position > 30 {
-                end_position = 30;
+    // `/* ... */`, where each `/*` inside needs its own `*/`
+    fn block_comment(&mut self) -> token::Token<'a> {
+        self.read_char();
+        let mut depth = 1;
+        while depth > 0 {
+            let rest = &self.input[self.position..];
+            if rest.is_empty() {
+                return self.token_from_source(token::TokenType::UnterminatedComment);
             }
-            let s: String = (&self.input[position..end_position]).to_string();
-            return self.get_token_with_val(
-                token::TokenType::UnterminatedString,
-                Box::leak(s.into_boxed_str()),
-            );
+            if rest.starts_with("/*") || rest.starts_with("*/") {
+                depth = if rest.starts_with("/*") {
+                    depth + 1
+                } else {
+                    depth - 1
+                };
+                self.read_char();
+            }
+            self.read_char();
+        }
+        self.token_from_source(token::TokenType::BlockComment)
+    }
+
+    // `"..."`, or `"""..."""` for text spanning several lines. Escapes are only
+    // checked here, the parser replaces them with `unescape`. Raw strings keep them.
+    fn get_string_token(&mut self, raw: bool) -> token::Token<'a> {
+        let triple = self.match_next_char('"') && self.peek_next_char() == '"';
+        if triple {
+            self.read_char();
+            self.read_char();
+            // the newline right after the opening quotes is not part of the text
+            if self.match_next_char('\n') {
+                self.read_char();
+            }
+        }
+
+        if raw {
+            return self.string_part(raw, triple, token::TokenType::RawString, None);
         }
+        self.string_part(
+            raw,
+            triple,
+            token::TokenType::String,
+            Some(token::TokenType::StringStart),
+        )
+    }
+
+    // The text of a string up to its closing quotes, or up to a `#{` embedding an
+    // expression when `interpolated` is the token type to use then
+    fn string_part(
+        &mut self,
+        raw: bool,
+        triple: bool,
+        closed: token::TokenType,
+        interpolated: Option<token::TokenType>,
+    ) -> token::Token<'a> {
+        let quotes = if triple { "\"\"\"" } else { "\"" };
+
+        let position = self.position;
+        let mut invalid_escape = None;
+        let token_type = loop {
+            let rest = &self.input[self.position..];
+            if rest.starts_with(quotes) {
+                break closed;
+            }
+            if let (Some(token_type), true) = (interpolated, rest.starts_with("#{")) {
+                break token_type;
+            }
+            if rest.is_empty() || (!triple && rest.starts_with('\n')) {
+                let input = self.input;
+                return self.get_token_with_val(
+                    token::TokenType::UnterminatedString,
+                    &input[position..self.position],
+                );
+            }
+
+            let (start, line, column) = (self.position, self.line, self.column);
+            if self.read_char() == Some('\\') && !raw && !self.read_escape() {
+                invalid_escape.get_or_insert(token::Token {
+                    token_type: token::TokenType::InvalidEscape,
+                    val: &self.input[start..self.position],
+                    line,
+                    column,
+                    span: Span::new(start, self.position),
+                });
+            }
+        };
+
+        let end = self.position;
+        if token_type == closed {
+            for _ in 0..quotes.len() {
+                self.read_char();
+            }
+        } else {
+            let (line, column) = (self.line, self.column);
+            self.read_char();
+            self.read_char();
+            self.interpolations.push(Interpolation {
+                braces: 0,
+                triple,
+                open: token::Token {
+                    token_type,
+                    val: &self.input[end..self.position],
+                    line,
+                    column,
+                    span: Span::new(end, self.position),
+                },
+            });
+        }
+
+        if let Some(token) = invalid_escape {
+            return token;
+        }
+        let input = self.input;
+        self.get_token_with_val(token_type, &input[position..end])
+    }
 
-        //skip ending '"'
-        self.increment_position();
+    // `{` and `}` inside an embedded expression, the `}` matching its `#{` resumes the string
+    fn brace_token(&mut self, c: char) -> token::Token<'a> {
+        let Some(interpolation) = self.interpolations.last_mut() else {
+            return self.single_char_token(if c == '{' {
+                token::TokenType::LeftBrace
+            } else {
+                token::TokenType::RightBrace
+            });
+        };
 
-        // Trim the surrounding quotes
-        let s: String = (&self.input[position..self.position - 1]).to_string();
-        return self.get_token_with_val(token::TokenType::String, Box::leak(s.into_boxed_str()));
+        match c {
+            '{' => {
+                interpolation.braces += 1;
+                self.single_char_token(token::TokenType::LeftBrace)
+            }
+            _ if interpolation.braces > 0 => {
+                interpolation.braces -= 1;
+                self.single_char_token(token::TokenType::RightBrace)
+            }
+            _ => {
+                let triple = interpolation.triple;
+                self.interpolations.pop();
+                self.string_part(
+                    false,
+                    triple,
+                    token::TokenType::StringEnd,
+                    Some(token::TokenType::StringMiddle),
+                )
+            }
+        }
     }
 
-    fn get_complex_token(&mut self, current_char: char) -> token::Token {
-        let position = self.position - 1;
-        if current_char.is_digit(10) {
-            //handle digit
-            while self.peek_char().is_digit(10) {
+    // Reads what follows a '\\' in a string, false when it is not a valid escape
+    fn read_escape(&mut self) -> bool {
+        match self.peek_char() {
+            'n' | 't' | 'r' | '\\' | '"' | '#' => {
                 self.read_char();
+                true
             }
-            if self.peek_char() == '.' && self.peek_next_char().is_digit(10) {
+            'u' => {
+                self.read_char();
+                if !self.match_next_char('{') {
+                    return false;
+                }
                 self.read_char();
-                while self.peek_char().is_digit(10) {
+                let digits = self.position;
+                while self.peek_char().is_ascii_alphanumeric() {
                     self.read_char();
                 }
+                let code = &self.input[digits..self.position];
+                if !self.match_next_char('}') {
+                    return false;
+                }
+                self.read_char();
+                code.len() <= 6
+                    && code.chars().all(|c| c.is_ascii_hexdigit())
+                    && u32::from_str_radix(code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .is_some()
+            }
+            // the quote or line end stays, so that the string can still end there
+            '\n' | '\0' => false,
+            _ => {
+                self.read_char();
+                false
             }
+        }
+    }
 
-            let s: String = (&self.input[position..self.position]).to_string();
-            return self
-                .get_token_with_val(token::TokenType::Number, Box::leak(s.into_boxed_str()));
+    fn get_complex_token(&mut self, current_char: char) -> token::Token<'a> {
+        if current_char == 'r' && self.match_next_char('"') {
+            self.read_char();
+            return self.get_string_token(true);
+        }
+        if current_char == 'b' && self.match_next_char('\'') {
+            self.read_char();
+            return self.get_byte_token();
+        }
+        if current_char.is_ascii_digit() {
+            return self.get_number_token(current_char);
         }
         if current_char.is_alphanumeric() {
             while self.peek_char().is_alphanumeric() || self.peek_char() == '_' {
                 self.read_char();
             }
 
-            let s: String = (&self.input[position..self.position]).to_string();
-            let token_str: &str = &s;
-            let token = KEYWORDS.get(&token_str);
+            let token_str = &self.input[self.start..self.position];
+            let token = KEYWORDS.get(token_str);
             match token {
-                Some(t) => return self.get_token_with_val(*t, Box::leak(s.into_boxed_str())),
-                None => {
-                    return self.get_token_with_val(
-                        token::TokenType::Identifier,
-                        Box::leak(s.into_boxed_str()),
-                    )
+                Some(t) => return self.token_from_source(*t),
+                None => return self.token_from_source(token::TokenType::Identifier),
+            }
+        }
+
+        self.token_from_source(token::TokenType::Illegal)
+    }
+
+    // Numbers are ints unless they have a fraction or an exponent. Ints can also be
+    // written in hex, octal or binary, and '_' can separate digits: `0xff`, `1_000`.
+    fn get_number_token(&mut self, first_digit: char) -> token::Token<'a> {
+        let mut token_type = token::TokenType::Int;
+        let prefixed = first_digit == '0' && matches!(self.peek_char(), 'x' | 'o' | 'b');
+        if prefixed {
+            self.read_char();
+        } else {
+            self.read_digits();
+            if self.peek_char() == '.' && self.peek_next_char().is_ascii_digit() {
+                token_type = token::TokenType::Float;
+                self.read_char();
+                self.read_digits();
+            }
+            let sign = matches!(self.peek_next_char(), '+' | '-') as usize;
+            let exponent = matches!(self.peek_char(), 'e' | 'E')
+                && self.peek_nth_char(1 + sign).is_ascii_digit();
+            if exponent {
+                token_type = token::TokenType::Float;
+                for _ in 0..1 + sign {
+                    self.read_char();
                 }
+                self.read_digits();
             }
         }
 
-        let s = String::from(current_char);
-        return self.get_token_with_val(token::TokenType::Illegal, Box::leak(s.into_boxed_str()));
+        // letters glued to the number, or a second fraction as in `1.2.3`
+        while self.peek_char().is_alphanumeric()
+            || self.peek_char() == '_'
+            || (self.peek_char() == '.' && self.peek_next_char().is_ascii_digit())
+        {
+            self.read_char();
+        }
+
+        let val = &self.input[self.start..self.position];
+        let valid = match token_type {
+            token::TokenType::Int => int_value(val).is_some(),
+            _ => float_value(val).is_some(),
+        };
+        if !valid {
+            token_type = token::TokenType::InvalidNumber;
+        }
+        self.token_from_source(token_type)
     }
 
-    fn single_char_token(&mut self, token_type: token::TokenType) -> token::Token {
+    // decimal digits and the '_' between them, which `int_value` and `float_value` check
+    fn read_digits(&mut self) {
+        while self.peek_char().is_ascii_digit() || self.peek_char() == '_' {
+            self.read_char();
+        }
+    }
+
+    // `b'a'` or `b'\n'`, the token holds the text between the quotes
+    fn get_byte_token(&mut self) -> token::Token<'a> {
+        let position = self.position;
+        while !matches!(self.peek_char(), '\'' | '\n' | '\0') {
+            if self.read_char() == Some('\\') && self.peek_char() != '\n' {
+                self.read_char();
+            }
+        }
+        let end = self.position;
+        let closed = self.match_next_char('\'');
+        if closed {
+            self.read_char();
+        }
+
+        let input = self.input;
+        let val = &input[position..end];
+        match byte_value(val) {
+            Some(_) if closed => self.get_token_with_val(token::TokenType::Byte, val),
+            _ => self.token_from_source(token::TokenType::InvalidByte),
+        }
+    }
+
+    fn single_char_token(&mut self, token_type: token::TokenType) -> token::Token<'a> {
         let val = token_type.as_str();
-        return self.get_token_with_val(token_type, val);
+        self.get_token_with_val(token_type, val)
+    }
+
+    // token whose value is the source text it was lexed from
+    fn token_from_source(&mut self, token_type: token::TokenType) -> token::Token<'a> {
+        let input = self.input;
+        let s = &input[self.start..self.position];
+        self.get_token_with_val(token_type, s)
     }
 
     fn get_token_with_val(
         &mut self,
         token_type: token::TokenType,
-        val: &'static str,
-    ) -> token::Token {
+        val: &'a str,
+    ) -> token::Token<'a> {
         token::Token {
-            col: self.position,
             token_type,
             val,
+            line: self.start_line,
+            column: self.start_column,
+            span: Span::new(self.start, self.position),
         }
     }
 
+    // remember where the next token begins
+    fn start_token(&mut self) {
+        self.start = self.position;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
     fn match_next_char(&mut self, expected_char: char) -> bool {
         let next_char = self.peek_char();
-        if expected_char == next_char {
-            return true;
-        } else {
-            return false;
-        }
+        expected_char == next_char
     }
 
     fn read_char(&mut self) -> Option<char> {
-        if self.position > self.input.len() {
-            return Some('\0');
+        let c = self.input[self.position..].chars().next()?;
+        self.position += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
         }
 
-        let c = self.input.chars().nth(self.position);
-        self.increment_position();
-
-        return c;
+        Some(c)
     }
 
     fn peek_char(&mut self) -> char {
-        if (self.read_position - 1) > self.input.len() {
-            return '\0';
-        }
-        match self.input.chars().nth(self.read_position - 1) {
-            Some(c) => return c,
-            None => return '\0',
-        }
+        self.input[self.position..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next_char(&mut self) -> char {
-        if self.read_position > self.input.len() {
-            return '\0';
-        }
-        match self.input.chars().nth(self.read_position) {
-            Some(c) => return c,
-            None => return '\0',
-        }
+        self.input[self.position..].chars().nth(1).unwrap_or('\0')
     }
 
-    fn increment_position(&mut self) {
-        self.position = self.position + 1;
-        self.read_position = self.position + 1;
+    fn peek_nth_char(&mut self, n: usize) -> char {
+        self.input[self.position..].chars().nth(n).unwrap_or('\0')
     }
 
     fn has_more_token(&self) -> bool {
@@ -329,17 +624,119 @@ impl Lexer {
     }
 }
 
-fn print_error(err: &str) {
-    println!("ERROR:{}", err);
-}
-
-pub fn new(input: String) -> Lexer {
+pub fn new(input: &str) -> Lexer<'_> {
     Lexer {
-        input: input.to_string(),
+        input,
         position: 0,
-        read_position: 0,
         line: 1,
+        column: 1,
+        start: 0,
+        start_line: 1,
+        start_column: 1,
+        interpolations: Vec::new(),
+    }
+}
+
+// The text of a string token with its escapes replaced, the lexer has checked them
+pub fn unescape(val: &str) -> String {
+    let mut text = String::with_capacity(val.len());
+    let mut chars = val.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some('r') => text.push('\r'),
+            Some('u') => {
+                let code: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                let c = u32::from_str_radix(&code, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+                    .expect("a unicode escape checked by the lexer");
+                text.push(c);
+            }
+            Some(c) => text.push(c),
+            None => {}
+        }
+    }
+    text
+}
+
+// The value of an int token such as `42`, `1_000`, `0xff`, `0o17` or `0b1010`,
+// none when it is malformed or does not fit in 64 bits
+pub fn int_value(val: &str) -> Option<i64> {
+    let (digits, radix) = match val.get(..2) {
+        Some("0x") => (&val[2..], 16),
+        Some("0o") => (&val[2..], 8),
+        Some("0b") => (&val[2..], 2),
+        _ => (val, 10),
+    };
+    let digits = without_separators(digits, |c| c.is_digit(radix))?;
+    i64::from_str_radix(&digits, radix).ok()
+}
+
+// The value of a float token such as `52.50`, `1e9` or `2.5E-3`
+pub fn float_value(val: &str) -> Option<f64> {
+    let n: f64 = without_separators(val, |c| c.is_ascii_digit())?
+        .parse()
+        .ok()?;
+    n.is_finite().then_some(n)
+}
+
+// `digits` without the '_' separating them, none when a '_' is not between two digits
+fn without_separators(digits: &str, is_digit: impl Fn(char) -> bool) -> Option<String> {
+    let chars: Vec<char> = digits.chars().collect();
+    for (i, c) in chars.iter().enumerate() {
+        let between_digits =
+            i > 0 && is_digit(chars[i - 1]) && chars.get(i + 1).is_some_and(|c| is_digit(*c));
+        if *c == '_' && !between_digits {
+            return None;
+        }
     }
+    // `from_str_radix` would accept a sign
+    if !chars.first().is_some_and(|c| is_digit(*c)) {
+        return None;
+    }
+    Some(digits.replace('_', ""))
+}
+
+// The value of the text between the quotes of a byte literal, e.g. `a` or `\n`
+pub fn byte_value(val: &str) -> Option<u8> {
+    let byte = match val.strip_prefix('\\') {
+        None => match val.as_bytes() {
+            [byte] if byte.is_ascii() => *byte,
+            _ => return None,
+        },
+        Some("n") => b'\n',
+        Some("t") => b'\t',
+        Some("r") => b'\r',
+        Some("0") => 0,
+        Some("\\") => b'\\',
+        Some("'") => b'\'',
+        Some(escape) => {
+            let hex = escape.strip_prefix('x')?;
+            if hex.len() != 2 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            u8::from_str_radix(hex, 16).ok()?
+        }
+    };
+    Some(byte)
+}
+
+// The text of consecutive `///` comments, without the slashes and the space after them
+pub fn doc_text(comments: &[token::Token]) -> String {
+    let lines: Vec<&str> = comments
+        .iter()
+        .map(|c| {
+            let text = &c.val[token::DOC_COMMENT.len()..];
+            text.strip_prefix(' ').unwrap_or(text)
+        })
+        .collect();
+    lines.join("\n")
 }
 
 pub fn mod_name() -> String {
@@ -355,12 +752,13 @@ mod tests {
     use std::collections::HashMap;
 
     // contains the map of all the keywords
-    fn test_cases_map() -> HashMap<String, Vec<token::Token>> {
+    fn test_cases_map() -> HashMap<&'static str, Vec<token::Token<'static>>> {
         let mut map = HashMap::new();
-        map.insert("x = 2 //this is puran\n".to_string(), test_tokens_1());
-        map.insert("val == 52.50 && y != 200".to_string(), test_tokens_2());
-        map.insert("y == \"this is my string\"".to_string(), test_tokens_3());
-        map.insert("let x = \"test\"".to_string(), test_tokens_4());
+        map.insert("x = 2 //this is puran\n", test_tokens_1());
+        map.insert("val == 52.50 && y != 200", test_tokens_2());
+        map.insert("y == \"this is my string\"", test_tokens_3());
+        map.insert("let x = \"test\"", test_tokens_4());
+        map.insert("let é = 1\n  x", test_tokens_5());
 
         map
     }
@@ -378,156 +776,496 @@ mod tests {
         }
     }
 
+    #[test]
+    fn diagnostics_test() {
+        let mut l = new("let x = $1\nlet y = @\nlet s = \"abc");
+        let diagnostics = l.parse().unwrap_err();
+        assert_eq!(3, diagnostics.len());
+
+        assert_eq!("unrecognized character '$'", diagnostics[0].message);
+        assert_eq!((1, 9), (diagnostics[0].line, diagnostics[0].column));
+        assert_eq!(Span::new(8, 9), diagnostics[0].span);
+
+        assert_eq!("unrecognized character '@'", diagnostics[1].message);
+        assert_eq!((2, 9), (diagnostics[1].line, diagnostics[1].column));
+
+        assert_eq!("unterminated string", diagnostics[2].message);
+        assert_eq!((3, 9), (diagnostics[2].line, diagnostics[2].column));
+        assert_eq!(Span::new(29, 33), diagnostics[2].span);
+        assert!(diagnostics[2].hint.is_some());
+    }
+
+    #[test]
+    fn loop_tokens_test() {
+        let tokens = new("for (v, i) in 0..10 { break }").parse().unwrap();
+        let types: Vec<token::TokenType> = tokens.iter().map(|t| t.token_type).collect();
+        assert_eq!(
+            vec![
+                token::TokenType::For,
+                token::TokenType::LeftParen,
+                token::TokenType::Identifier,
+                token::TokenType::Comma,
+                token::TokenType::Identifier,
+                token::TokenType::RightParen,
+                token::TokenType::In,
+                token::TokenType::Int,
+                token::TokenType::DotDot,
+                token::TokenType::Int,
+                token::TokenType::LeftBrace,
+                token::TokenType::Break,
+                token::TokenType::RightBrace,
+                token::TokenType::EndOfFile,
+            ],
+            types
+        );
+        assert_eq!("0", tokens[7].val);
+
+        let tokens = new("!a != b").parse().unwrap();
+        assert_eq!(token::TokenType::Not, tokens[0].token_type);
+        assert_eq!(token::TokenType::NotEqual, tokens[2].token_type);
+
+        let tokens = new("a ** b ~/ c % d += -= *= /= %= // done\n")
+            .parse()
+            .unwrap();
+        let types: Vec<token::TokenType> = tokens
+            .iter()
+            .map(|t| t.token_type)
+            .filter(|t| *t != token::TokenType::Identifier)
+            .collect();
+        assert_eq!(
+            vec![
+                token::TokenType::Power,
+                token::TokenType::IntDivide,
+                token::TokenType::Modulo,
+                token::TokenType::PlusAssign,
+                token::TokenType::MinusAssign,
+                token::TokenType::MultiplyAssign,
+                token::TokenType::DivideAssign,
+                token::TokenType::ModuloAssign,
+                token::TokenType::Comment,
+                token::TokenType::Newline,
+                token::TokenType::EndOfFile,
+            ],
+            types
+        );
+
+        let tokens = new("m.f(1.5)").parse().unwrap();
+        assert_eq!(token::TokenType::Dot, tokens[1].token_type);
+        assert_eq!("1.5", tokens[4].val);
+    }
+
+    #[test]
+    fn string_test() {
+        let tokens = new(r#"let s = "say \"hi\"\n\t\\ \u{e9}""#).parse().unwrap();
+        assert_eq!(token::TokenType::String, tokens[3].token_type);
+        assert_eq!(r#"say \"hi\"\n\t\\ \u{e9}"#, tokens[3].val);
+        assert_eq!("say \"hi\"\n\t\\ é", unescape(tokens[3].val));
+
+        let tokens = new(r#"r"C:\dir\n" + """
+line "one"
+line \t two""""#)
+        .parse()
+        .unwrap();
+        assert_eq!(token::TokenType::RawString, tokens[0].token_type);
+        assert_eq!(r"C:\dir\n", tokens[0].val);
+        assert_eq!(Span::new(0, 11), tokens[0].span);
+        assert_eq!(token::TokenType::String, tokens[2].token_type);
+        assert_eq!("line \"one\"\nline \t two", unescape(tokens[2].val));
+
+        // plain strings end with their line
+        let diagnostics = new("let s = \"abc\nlet t = 1").parse().unwrap_err();
+        assert_eq!("unterminated string", diagnostics[0].message);
+        assert_eq!(Span::new(8, 12), diagnostics[0].span);
+    }
+
+    #[test]
+    fn interpolation_test() {
+        let tokens = new(r#""a #{m["k"]} b #{ {x: 1} } \#{c}""#).parse().unwrap();
+        let parts: Vec<(token::TokenType, &str)> =
+            tokens.iter().map(|t| (t.token_type, t.val)).collect();
+        assert_eq!(
+            vec![
+                (token::TokenType::StringStart, "a "),
+                (token::TokenType::Identifier, "m"),
+                (token::TokenType::LeftBracket, "["),
+                (token::TokenType::String, "k"),
+                (token::TokenType::RightBracket, "]"),
+                (token::TokenType::StringMiddle, " b "),
+                (token::TokenType::LeftBrace, "{"),
+                (token::TokenType::Identifier, "x"),
+                (token::TokenType::Colon, ":"),
+                (token::TokenType::Int, "1"),
+                (token::TokenType::RightBrace, "}"),
+                (token::TokenType::StringEnd, r" \#{c}"),
+                (token::TokenType::EndOfFile, "EOF"),
+            ],
+            parts
+        );
+        assert_eq!(Span::new(0, 5), tokens[0].span);
+        assert_eq!(Span::new(11, 17), tokens[5].span);
+
+        let diagnostics = new("\"a #{b + 1\n").parse().unwrap_err();
+        assert_eq!("unterminated string interpolation", diagnostics[0].message);
+        assert_eq!(Span::new(3, 5), diagnostics[0].span);
+    }
+
+    #[test]
+    fn invalid_escape_test() {
+        let diagnostics = new("let s = \"ok\"\nlet t = \"a\\qb \\u{110000}\"")
+            .parse()
+            .unwrap_err();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("invalid escape sequence '\\q'", diagnostics[0].message);
+        assert_eq!((2, 11), (diagnostics[0].line, diagnostics[0].column));
+        assert_eq!(Span::new(23, 25), diagnostics[0].span);
+
+        let diagnostics = new("\"\\u{zz}\" \"\\u{1F600}\"").parse().unwrap_err();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("invalid escape sequence '\\u{zz}'", diagnostics[0].message);
+    }
+
+    #[test]
+    fn number_test() {
+        let tokens = new("0xff 0o17 0b1010 1_000_000 1e9 2.5E-3 b'a' b'\\n' 1..2")
+            .parse()
+            .unwrap();
+        let parts: Vec<(token::TokenType, &str)> =
+            tokens.iter().map(|t| (t.token_type, t.val)).collect();
+        assert_eq!(
+            vec![
+                (token::TokenType::Int, "0xff"),
+                (token::TokenType::Int, "0o17"),
+                (token::TokenType::Int, "0b1010"),
+                (token::TokenType::Int, "1_000_000"),
+                (token::TokenType::Float, "1e9"),
+                (token::TokenType::Float, "2.5E-3"),
+                (token::TokenType::Byte, "a"),
+                (token::TokenType::Byte, "\\n"),
+                (token::TokenType::Int, "1"),
+                (token::TokenType::DotDot, ".."),
+                (token::TokenType::Int, "2"),
+                (token::TokenType::EndOfFile, "EOF"),
+            ],
+            parts
+        );
+        assert_eq!(Some(255), int_value("0xff"));
+        assert_eq!(Some(1_000_000), int_value("1_000_000"));
+        assert_eq!(None, int_value("9223372036854775808"));
+        assert_eq!(Some(0.0025), float_value("2.5E-3"));
+        assert_eq!(Some(b'\n'), byte_value("\\n"));
+        assert_eq!(Some(0x7f), byte_value("\\x7f"));
+
+        let diagnostics = new("12abc 1.2.3 1_ 0x 9223372036854775808 b'ab'")
+            .parse()
+            .unwrap_err();
+        let messages: Vec<&str> = diagnostics.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(
+            vec![
+                "invalid number '12abc'",
+                "invalid number '1.2.3'",
+                "invalid number '1_'",
+                "invalid number '0x'",
+                "invalid number '9223372036854775808'",
+                "invalid byte literal b'ab'",
+            ],
+            messages
+        );
+        assert_eq!(Span::new(0, 5), diagnostics[0].span);
+        assert_eq!(Span::new(6, 11), diagnostics[1].span);
+    }
+
+    #[test]
+    fn comment_test() {
+        let tokens = new("/// doc\n//// line\nx /* a /* b */ c */ y // end")
+            .parse()
+            .unwrap();
+        let parts: Vec<(token::TokenType, &str)> =
+            tokens.iter().map(|t| (t.token_type, t.val)).collect();
+        assert_eq!(
+            vec![
+                (token::TokenType::DocComment, "/// doc"),
+                (token::TokenType::Newline, "NEWLINE"),
+                (token::TokenType::Comment, "//// line"),
+                (token::TokenType::Newline, "NEWLINE"),
+                (token::TokenType::Identifier, "x"),
+                (token::TokenType::BlockComment, "/* a /* b */ c */"),
+                (token::TokenType::Identifier, "y"),
+                (token::TokenType::Comment, "// end"),
+                (token::TokenType::EndOfFile, "EOF"),
+            ],
+            parts
+        );
+        assert_eq!(Span::new(20, 37), tokens[5].span);
+
+        let diagnostics = new("x /* a /* b */\ny").parse().unwrap_err();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!("unterminated block comment", diagnostics[0].message);
+        assert_eq!(Span::new(2, 4), diagnostics[0].span);
+    }
+
+    #[test]
+    fn tokens_borrow_from_input_test() {
+        let input = String::from("let name = \"rusty\" // comment\n");
+        let tokens = new(&input).parse().unwrap();
+
+        // identifiers, keywords and strings point into the input instead of owning a copy
+        let range = input.as_bytes().as_ptr_range();
+        for t in &tokens[..2] {
+            assert!(range.contains(&t.val.as_ptr()), "{:?} was copied", t);
+        }
+        assert_eq!("rusty", tokens[3].val);
+        assert!(range.contains(&tokens[3].val.as_ptr()));
+    }
+
     fn lexer_test(mut l: Lexer, expected_tokens: Vec<token::Token>) {
-        println!("testing testing testn");
-        let tokens = l.parse();
-        for (index, t) in tokens.iter().enumerate() {
-            let expected_t: Option<&token::Token> = expected_tokens.get(index);
-            match expected_t {
-                Some(expected_t) => {
-                    println!("returned token: {:?}", t);
-                    assert_eq!(expected_t.val, t.val);
-                    assert_eq!(expected_t.token_type, t.token_type);
-                }
-                None => {
-                    println!("failed expected assertion on {:?}", t);
-                    assert!(false);
-                }
-            }
+        let tokens = l.parse().unwrap();
+        assert_eq!(expected_tokens.len(), tokens.len());
+        for (t, expected_t) in tokens.iter().zip(expected_tokens.iter()) {
+            assert_eq!(expected_t.val, t.val);
+            assert_eq!(expected_t.token_type, t.token_type);
+            assert_eq!(
+                (expected_t.line, expected_t.column),
+                (t.line, t.column),
+                "position of {:?}",
+                t
+            );
+            assert_eq!(expected_t.span, t.span, "span of {:?}", t);
         }
+    }
 
-        println!("test passed");
-    }
-
-    fn test_tokens_1() -> Vec<token::Token> {
-        let mut output_tokens: Vec<token::Token> = Vec::new();
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Identifier,
-            val: "x",
-            col: 1,
-        });
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Assign,
-            val: "=",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Number,
-            val: "2",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Comment,
-            val: "//",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Newline,
-            val: "NEWLINE",
-            col: 4,
-        });
-
-        output_tokens
-    }
-
-    fn test_tokens_2() -> Vec<token::Token> {
-        let mut output_tokens: Vec<token::Token> = Vec::new();
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Identifier,
-            val: "val",
-            col: 1,
-        });
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Equal,
-            val: "==",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Number,
-            val: "52.50",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::And,
-            val: "&&",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Identifier,
-            val: "y",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::NotEqual,
-            val: "!=",
-            col: 4,
-        });
-
-        output_tokens.push(token::Token {
-            token_type: token::TokenType::Number,
-            val: "200",
-            col: 4,
-        });
-
-        output_tokens
+    fn test_tokens_1() -> Vec<token::Token<'static>> {
+        vec![
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "x",
+                line: 1,
+                column: 1,
+                span: Span::new(0, 1),
+            },
+            token::Token {
+                token_type: token::TokenType::Assign,
+                val: "=",
+                line: 1,
+                column: 3,
+                span: Span::new(2, 3),
+            },
+            token::Token {
+                token_type: token::TokenType::Int,
+                val: "2",
+                line: 1,
+                column: 5,
+                span: Span::new(4, 5),
+            },
+            token::Token {
+                token_type: token::TokenType::Comment,
+                val: "//this is puran",
+                line: 1,
+                column: 7,
+                span: Span::new(6, 21),
+            },
+            token::Token {
+                token_type: token::TokenType::Newline,
+                val: "NEWLINE",
+                line: 1,
+                column: 22,
+                span: Span::new(21, 22),
+            },
+            token::Token {
+                token_type: token::TokenType::EndOfFile,
+                val: "EOF",
+                line: 2,
+                column: 1,
+                span: Span::new(22, 22),
+            },
+        ]
     }
-}
 
-fn test_tokens_3() -> Vec<token::Token> {
-    let mut output_tokens: Vec<token::Token> = Vec::new();
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::Identifier,
-        val: "y",
-        col: 1,
-    });
-
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::Equal,
-        val: "==",
-        col: 1,
-    });
-
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::String,
-        val: "this is my string",
-        col: 1,
-    });
-
-    return output_tokens;
-}
+    fn test_tokens_2() -> Vec<token::Token<'static>> {
+        vec![
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "val",
+                line: 1,
+                column: 1,
+                span: Span::new(0, 3),
+            },
+            token::Token {
+                token_type: token::TokenType::Equal,
+                val: "==",
+                line: 1,
+                column: 5,
+                span: Span::new(4, 6),
+            },
+            token::Token {
+                token_type: token::TokenType::Float,
+                val: "52.50",
+                line: 1,
+                column: 8,
+                span: Span::new(7, 12),
+            },
+            token::Token {
+                token_type: token::TokenType::And,
+                val: "&&",
+                line: 1,
+                column: 14,
+                span: Span::new(13, 15),
+            },
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "y",
+                line: 1,
+                column: 17,
+                span: Span::new(16, 17),
+            },
+            token::Token {
+                token_type: token::TokenType::NotEqual,
+                val: "!=",
+                line: 1,
+                column: 19,
+                span: Span::new(18, 20),
+            },
+            token::Token {
+                token_type: token::TokenType::Int,
+                val: "200",
+                line: 1,
+                column: 22,
+                span: Span::new(21, 24),
+            },
+            token::Token {
+                token_type: token::TokenType::EndOfFile,
+                val: "EOF",
+                line: 1,
+                column: 25,
+                span: Span::new(24, 24),
+            },
+        ]
+    }
 
-fn test_tokens_4() -> Vec<token::Token> {
-    let mut output_tokens: Vec<token::Token> = Vec::new();
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::Let,
-        val: "let",
-        col: 1,
-    });
-
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::Identifier,
-        val: "x",
-        col: 1,
-    });
-
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::Assign,
-        val: "=",
-        col: 1,
-    });
-
-    output_tokens.push(token::Token {
-        token_type: token::TokenType::String,
-        val: "test",
-        col: 1,
-    });
-
-    return output_tokens;
+    fn test_tokens_3() -> Vec<token::Token<'static>> {
+        vec![
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "y",
+                line: 1,
+                column: 1,
+                span: Span::new(0, 1),
+            },
+            token::Token {
+                token_type: token::TokenType::Equal,
+                val: "==",
+                line: 1,
+                column: 3,
+                span: Span::new(2, 4),
+            },
+            token::Token {
+                token_type: token::TokenType::String,
+                val: "this is my string",
+                line: 1,
+                column: 6,
+                span: Span::new(5, 24),
+            },
+            token::Token {
+                token_type: token::TokenType::EndOfFile,
+                val: "EOF",
+                line: 1,
+                column: 25,
+                span: Span::new(24, 24),
+            },
+        ]
+    }
+
+    fn test_tokens_4() -> Vec<token::Token<'static>> {
+        vec![
+            token::Token {
+                token_type: token::TokenType::Let,
+                val: "let",
+                line: 1,
+                column: 1,
+                span: Span::new(0, 3),
+            },
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "x",
+                line: 1,
+                column: 5,
+                span: Span::new(4, 5),
+            },
+            token::Token {
+                token_type: token::TokenType::Assign,
+                val: "=",
+                line: 1,
+                column: 7,
+                span: Span::new(6, 7),
+            },
+            token::Token {
+                token_type: token::TokenType::String,
+                val: "test",
+                line: 1,
+                column: 9,
+                span: Span::new(8, 14),
+            },
+            token::Token {
+                token_type: token::TokenType::EndOfFile,
+                val: "EOF",
+                line: 1,
+                column: 15,
+                span: Span::new(14, 14),
+            },
+        ]
+    }
+
+    // multi-byte chars count as one column but take up several bytes in the span
+    fn test_tokens_5() -> Vec<token::Token<'static>> {
+        vec![
+            token::Token {
+                token_type: token::TokenType::Let,
+                val: "let",
+                line: 1,
+                column: 1,
+                span: Span::new(0, 3),
+            },
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "é",
+                line: 1,
+                column: 5,
+                span: Span::new(4, 6),
+            },
+            token::Token {
+                token_type: token::TokenType::Assign,
+                val: "=",
+                line: 1,
+                column: 7,
+                span: Span::new(7, 8),
+            },
+            token::Token {
+                token_type: token::TokenType::Int,
+                val: "1",
+                line: 1,
+                column: 9,
+                span: Span::new(9, 10),
+            },
+            token::Token {
+                token_type: token::TokenType::Newline,
+                val: "NEWLINE",
+                line: 1,
+                column: 10,
+                span: Span::new(10, 11),
+            },
+            token::Token {
+                token_type: token::TokenType::Identifier,
+                val: "x",
+                line: 2,
+                column: 3,
+                span: Span::new(13, 14),
+            },
+            token::Token {
+                token_type: token::TokenType::EndOfFile,
+                val: "EOF",
+                line: 2,
+                column: 4,
+                span: Span::new(14, 14),
+            },
+        ]
+    }
 }
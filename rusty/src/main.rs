@@ -1,31 +1,408 @@
+use std::env;
+use std::fs;
 use std::io;
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::thread;
 
+use diagnostic::{Diagnostic, Severity};
+use interpreter::SourceFile;
+use std::rc::Rc;
+
+mod ast;
+mod checker;
+mod diagnostic;
+mod environment;
+mod interpreter;
 mod lexer;
+mod methods;
 mod parser;
 mod token;
+mod value;
+
+// exit codes, following the BSD sysexits convention where one fits
+const EXIT_OK: i32 = 0;
+const EXIT_TYPE_ERROR: i32 = 1; // the script parses but does not type check
+const EXIT_USAGE: i32 = 64;
+const EXIT_SYNTAX_ERROR: i32 = 65; // lexical or parse error
+const EXIT_NO_INPUT: i32 = 66;
+const EXIT_RUNTIME_ERROR: i32 = 70;
+
+const USAGE: &str = "usage: rusty [command] [file]
+
+commands:
+    run <file>      type check a script, then run it
+    check <file>    type check a script without running it
+    repl            start the interactive shell (default)
+    tokens <file>   print the tokens of a script
+    ast <file>      print the syntax tree of a script
+
+`import` looks for modules next to the script, then in the directories
+listed in RUSTY_PATH.
+";
+
+// Each call in a script nests several native calls of the interpreter, the main
+// thread's stack would overflow well before MAX_CALL_DEPTH
+const STACK_SIZE: usize = 512 * 1024 * 1024;
 
 fn main() {
+    let code = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run_command)
+        .expect("cannot start the interpreter thread")
+        .join()
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+    process::exit(code);
+}
+
+fn run_command() -> i32 {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        [] | ["repl"] => repl(),
+        ["run", path] => with_source(path, |source| run_file(path, source)),
+        ["check", path] => with_source(path, check_file),
+        ["tokens", path] => with_source(path, print_tokens),
+        ["ast", path] => with_source(path, print_ast),
+        ["help"] | ["-h"] | ["--help"] => {
+            print!("{}", USAGE);
+            EXIT_OK
+        }
+        _ => {
+            eprint!("{}", USAGE);
+            EXIT_USAGE
+        }
+    }
+}
+
+fn with_source(path: &str, command: impl Fn(&str) -> i32) -> i32 {
+    match fs::read_to_string(path) {
+        Ok(source) => command(&source),
+        Err(err) => {
+            eprintln!("error: cannot read '{}': {}", path, err);
+            EXIT_NO_INPUT
+        }
+    }
+}
+
+fn repl() -> i32 {
     println!("Welcome to Rusty!");
     println!("Type Ctrl+C to exit the shell");
+    let mut interpreter = interpreter::new();
+    interpreter.set_search_path(search_path(Path::new(".")));
+    // knows the bindings of the earlier lines, like the interpreter does
+    let mut checker = checker::new();
+    let mut count = 0;
     loop {
         print!("rty>");
 
         io::stdout().flush().unwrap();
 
         let mut input = String::new();
-        io::stdin()
+        let read = io::stdin()
             .read_line(&mut input)
             .expect("Error reading the input");
 
-        if input.len() == 0 || input.len() == 1 {
+        // end of input (Ctrl+D or a closed pipe)
+        if read == 0 {
+            println!();
+            return EXIT_OK;
+        }
+
+        if input.len() == 1 {
             continue;
         }
+        count += 1;
+        let line = Rc::new(SourceFile {
+            path: PathBuf::from(format!("<input {}>", count)),
+            text: input,
+        });
+        run_line(&mut interpreter, &mut checker, line);
+    }
+}
+
+// Runs one line of the REPL. The checker only keeps the bindings of a line that
+// ran without errors, the others may never have been defined. Each line is a
+// source file of its own, which errors in the functions it declares point into.
+fn run_line(
+    interpreter: &mut interpreter::Interpreter,
+    checker: &mut checker::Checker,
+    line: Rc<SourceFile>,
+) -> i32 {
+    let input = &line.text;
+    let statements = match compile(input) {
+        Ok(statements) => statements,
+        Err(diagnostics) => {
+            report(&diagnostics, input);
+            return EXIT_SYNTAX_ERROR;
+        }
+    };
+    let before = checker.clone();
+    if let Err(diagnostics) = type_check(checker, &statements, input) {
+        report(&diagnostics, input);
+        *checker = before;
+        return EXIT_TYPE_ERROR;
+    }
+
+    interpreter.set_file(Some(line.clone()));
+    match interpreter.interpret(&statements) {
+        // echo the value of a trailing expression, like most shells do
+        Ok(value) => {
+            if let Some(ast::Stmt::Expression { .. }) = statements.last() {
+                println!("{}", value);
+            }
+            EXIT_OK
+        }
+        Err(err) => {
+            report_runtime_error(&err, input);
+            *checker = before;
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+fn run_file(path: &str, source: &str) -> i32 {
+    let dir = Path::new(path).parent().unwrap_or(Path::new("."));
+    let mut interpreter = interpreter::new();
+    interpreter.set_search_path(search_path(dir));
+    run_source(&mut interpreter, source)
+}
+
+// `dir` followed by the directories in RUSTY_PATH
+fn search_path(dir: &Path) -> Vec<PathBuf> {
+    let mut dirs = vec![dir.to_path_buf()];
+    if let Some(paths) = env::var_os("RUSTY_PATH") {
+        dirs.extend(env::split_paths(&paths));
+    }
+    dirs
+}
+
+fn run_source(interpreter: &mut interpreter::Interpreter, source: &str) -> i32 {
+    let statements = match compile(source) {
+        Ok(statements) => statements,
+        Err(diagnostics) => {
+            report(&diagnostics, source);
+            return EXIT_SYNTAX_ERROR;
+        }
+    };
+    if let Err(diagnostics) = type_check(&mut checker::new(), &statements, source) {
+        report(&diagnostics, source);
+        return EXIT_TYPE_ERROR;
+    }
+
+    match interpreter.interpret(&statements) {
+        Ok(_) => EXIT_OK,
+        Err(err) => {
+            report_runtime_error(&err, source);
+            EXIT_RUNTIME_ERROR
+        }
+    }
+}
+
+fn check_file(source: &str) -> i32 {
+    let statements = match compile(source) {
+        Ok(statements) => statements,
+        Err(diagnostics) => {
+            report(&diagnostics, source);
+            return EXIT_SYNTAX_ERROR;
+        }
+    };
+    match type_check(&mut checker::new(), &statements, source) {
+        Ok(()) => EXIT_OK,
+        Err(diagnostics) => {
+            report(&diagnostics, source);
+            EXIT_TYPE_ERROR
+        }
+    }
+}
+
+fn print_tokens(source: &str) -> i32 {
+    match lexer::new(source).parse() {
+        Ok(tokens) => {
+            for token in tokens {
+                println!(
+                    "{}:{} {} {:?}",
+                    token.line, token.column, token.token_type, token.val
+                );
+            }
+            EXIT_OK
+        }
+        Err(diagnostics) => {
+            report(&diagnostics, source);
+            EXIT_SYNTAX_ERROR
+        }
+    }
+}
+
+fn print_ast(source: &str) -> i32 {
+    match compile(source) {
+        Ok(statements) => {
+            for stmt in statements {
+                println!("{}", stmt);
+            }
+            EXIT_OK
+        }
+        Err(diagnostics) => {
+            report(&diagnostics, source);
+            EXIT_SYNTAX_ERROR
+        }
+    }
+}
+
+// Lexes and parses `source` into statements ready to be run
+fn compile(source: &str) -> Result<Vec<ast::Stmt>, Vec<Diagnostic>> {
+    let tokens = lexer::new(source).parse()?;
+    let mut parser = parser::new(tokens);
+    let result = parser.parse();
+    report(parser.warnings(), source);
+    result.map_err(|errors| errors.iter().map(|err| err.to_diagnostic(source)).collect())
+}
+
+fn type_check(
+    checker: &mut checker::Checker,
+    statements: &[ast::Stmt],
+    source: &str,
+) -> Result<(), Vec<Diagnostic>> {
+    checker
+        .check(statements)
+        .map_err(|errors| errors.iter().map(|err| err.to_diagnostic(source)).collect())
+}
+
+// the text of `file`, or of the main script when there is none
+fn source_of<'a>(file: &'a Option<Rc<SourceFile>>, main: &'a str) -> &'a str {
+    match file {
+        Some(file) => &file.text,
+        None => main,
+    }
+}
+
+fn report(diagnostics: &[Diagnostic], source: &str) {
+    for diagnostic in diagnostics {
+        eprint!("{}", diagnostic.render(source));
+    }
+}
+
+// Reports an error that nothing handled, followed by the calls it unwound through.
+// Positions inside imported modules, or earlier lines of the REPL, are shown
+// against their own source.
+fn report_runtime_error(err: &interpreter::RuntimeError, source: &str) {
+    let mut diagnostic = Diagnostic::at_span(
+        Severity::Error,
+        err.describe(),
+        err.span,
+        source_of(&err.file, source),
+    );
+    if let Some(file) = &err.file {
+        diagnostic = diagnostic.with_file(&file.path.display().to_string());
+    }
+    report(&[diagnostic], source_of(&err.file, source));
+
+    // a run of the same call, as in a deep recursion, is shown once
+    let mut frames = err.trace.iter().peekable();
+    while let Some(frame) = frames.next() {
+        let mut repeats = 0;
+        while frames.next_if(|next| *next == frame).is_some() {
+            repeats += 1;
+        }
+
+        let (line, column) =
+            diagnostic::line_and_column(source_of(&frame.file, source), frame.span.start);
+        match &frame.file {
+            Some(file) => eprintln!(
+                "  at {} ({}, line {}, col {})",
+                frame.function,
+                file.path.display(),
+                line,
+                column
+            ),
+            None => eprintln!("  at {} (line {}, col {})", frame.function, line, column),
+        }
+        if repeats > 0 {
+            eprintln!("  ... the same call {} more time(s)", repeats);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn exit_code_test() {
+        let mut interpreter = interpreter::new();
+        assert_eq!(
+            EXIT_OK,
+            run_source(&mut interpreter, "var x = 1\nx = x + 1\n")
+        );
+        assert_eq!(EXIT_SYNTAX_ERROR, run_source(&mut interpreter, "let x = $"));
+        assert_eq!(EXIT_SYNTAX_ERROR, run_source(&mut interpreter, "(1 + 2"));
+        assert_eq!(EXIT_TYPE_ERROR, run_source(&mut interpreter, "1 + \"a\""));
+        assert_eq!(EXIT_RUNTIME_ERROR, run_source(&mut interpreter, "5 % 0"));
+        // an int returned as a float is still a valid index
+        let source = "def f(): float { 1 }\nlet xs = [10, 20]\nxs[f()]";
+        assert_eq!(EXIT_OK, check_file(source));
+        assert_eq!(EXIT_OK, run_source(&mut interpreter, source));
+        assert_eq!(EXIT_OK, check_file("def f(a: int): int { a * 2 }\nf(2)"));
+        assert_eq!(EXIT_TYPE_ERROR, check_file("def f(a: int) { a }\nf(\"x\")"));
+        // a script that does not parse can be told apart from one that does not type check
+        assert_ne!(EXIT_SYNTAX_ERROR, check_file("1 + \"a\""));
+    }
+
+    #[test]
+    fn repl_test() {
+        let mut interpreter = interpreter::new();
+        let mut checker = checker::new();
+        let mut count = 0;
+        let mut run = |input: &str| {
+            count += 1;
+            let line = Rc::new(SourceFile {
+                path: PathBuf::from(format!("<input {}>", count)),
+                text: input.to_string(),
+            });
+            run_line(&mut interpreter, &mut checker, line)
+        };
+        assert_eq!(EXIT_OK, run("var x = 1"));
+        assert_eq!(EXIT_OK, run("x = \"a\""));
+        assert_eq!(EXIT_OK, run("x + \"b\""));
+
+        // the bindings of a line that failed are forgotten, by the checker as well
+        assert_eq!(EXIT_TYPE_ERROR, run("let y = 2\ny - \"a\""));
+        assert_eq!(EXIT_RUNTIME_ERROR, run("y - \"a\""));
+        assert_eq!(EXIT_RUNTIME_ERROR, run("let z = 1 % 0"));
+        assert_eq!(EXIT_RUNTIME_ERROR, run("z - \"a\""));
+
+        // an error in a function declared on an earlier line points into that line
+        assert_eq!(EXIT_OK, run("def g(a) { [0][0] + a - 1 }"));
+        let source = "let padding_padding_padding = g(\"x\")";
+        let line = Rc::new(SourceFile {
+            path: PathBuf::from("<input 9>"),
+            text: source.to_string(),
+        });
+        interpreter.set_file(Some(line));
+        let err = interpreter
+            .interpret(&compile(source).unwrap())
+            .unwrap_err();
+        assert_eq!(
+            Some(PathBuf::from("<input 8>")),
+            err.file.map(|f| f.path.clone())
+        );
+        assert_eq!(
+            Some(PathBuf::from("<input 9>")),
+            err.trace[0].file.as_ref().map(|f| f.path.clone())
+        );
+    }
 
-        let mut lexer = lexer::new(input);
-        let tokens = lexer.parse();
+    #[test]
+    fn comments_test() {
+        let statements = compile("// header\nlet x = 1 // trailing\n").unwrap();
+        assert_eq!(1, statements.len());
 
-        let mut parser = parser::new(tokens);
-        parser.parse();
+        // a comment ending the input without a newline
+        let statements = compile("let x = 1\n/* a /* nested */ one */ x // last").unwrap();
+        assert_eq!(2, statements.len());
     }
 }
@@ -0,0 +1,304 @@
+use crate::interpreter::{position, runtime_error, RuntimeError};
+use crate::token::Span;
+use crate::value::{Map, Value};
+use std::rc::Rc;
+
+// A method of a built-in type, called as `receiver.name(args)`
+pub struct Method {
+    pub name: &'static str,
+    pub arity: usize,
+    // changes the receiver, which is then written back to the variable holding it
+    pub mutates: bool,
+    run: fn(&mut Value, Vec<Value>, Span) -> Result<Value, RuntimeError>,
+}
+
+impl Method {
+    pub fn call(
+        &self,
+        receiver: &mut Value,
+        args: Vec<Value>,
+        span: Span,
+    ) -> Result<Value, RuntimeError> {
+        if args.len() != self.arity {
+            return Err(runtime_error(
+                format!(
+                    "'{}' expects {} argument(s), found {}",
+                    self.name,
+                    self.arity,
+                    args.len()
+                ),
+                span,
+            ));
+        }
+        (self.run)(receiver, args, span)
+    }
+}
+
+// The method `name` of the type of `receiver`, if it has one
+pub fn lookup(receiver: &Value, name: &str) -> Option<&'static Method> {
+    find(receiver.type_name(), name)
+}
+
+// Like lookup, for a type known by its name only, e.g. by the type checker
+pub fn find(type_name: &str, name: &str) -> Option<&'static Method> {
+    let methods = match type_name {
+        "string" => STRING_METHODS,
+        "list" => LIST_METHODS,
+        "map" => MAP_METHODS,
+        _ => return None,
+    };
+    methods.iter().find(|method| method.name == name)
+}
+
+// whether a method called `name` changes its receiver, for any type that has one
+pub fn is_mutating(name: &str) -> bool {
+    [STRING_METHODS, LIST_METHODS, MAP_METHODS]
+        .iter()
+        .flat_map(|methods| methods.iter())
+        .any(|method| method.name == name && method.mutates)
+}
+
+static STRING_METHODS: &[Method] = &[
+    Method {
+        name: "len",
+        arity: 0,
+        mutates: false,
+        run: |s, _, _| Ok(Value::Int(string(s).chars().count() as i64)),
+    },
+    Method {
+        name: "upper",
+        arity: 0,
+        mutates: false,
+        run: |s, _, _| Ok(Value::String(string(s).to_uppercase())),
+    },
+    Method {
+        name: "lower",
+        arity: 0,
+        mutates: false,
+        run: |s, _, _| Ok(Value::String(string(s).to_lowercase())),
+    },
+    Method {
+        name: "trim",
+        arity: 0,
+        mutates: false,
+        run: |s, _, _| Ok(Value::String(string(s).trim().to_string())),
+    },
+    Method {
+        name: "contains",
+        arity: 1,
+        mutates: false,
+        run: |s, args, span| {
+            let part = string_arg("contains", &args[0], span)?;
+            Ok(Value::Bool(string(s).contains(part)))
+        },
+    },
+    Method {
+        name: "starts_with",
+        arity: 1,
+        mutates: false,
+        run: |s, args, span| {
+            let prefix = string_arg("starts_with", &args[0], span)?;
+            Ok(Value::Bool(string(s).starts_with(prefix)))
+        },
+    },
+    Method {
+        name: "ends_with",
+        arity: 1,
+        mutates: false,
+        run: |s, args, span| {
+            let suffix = string_arg("ends_with", &args[0], span)?;
+            Ok(Value::Bool(string(s).ends_with(suffix)))
+        },
+    },
+    Method {
+        name: "split",
+        arity: 1,
+        mutates: false,
+        run: |s, args, span| {
+            let separator = string_arg("split", &args[0], span)?;
+            let parts = string(s)
+                .split(separator)
+                .map(|part| Value::String(part.to_string()))
+                .collect();
+            Ok(Value::List(Rc::new(parts)))
+        },
+    },
+];
+
+static LIST_METHODS: &[Method] = &[
+    Method {
+        name: "len",
+        arity: 0,
+        mutates: false,
+        run: |xs, _, _| Ok(Value::Int(list(xs).len() as i64)),
+    },
+    Method {
+        name: "contains",
+        arity: 1,
+        mutates: false,
+        run: |xs, args, _| Ok(Value::Bool(list(xs).contains(&args[0]))),
+    },
+    Method {
+        name: "join",
+        arity: 1,
+        mutates: false,
+        run: |xs, args, span| {
+            let separator = string_arg("join", &args[0], span)?;
+            let items: Vec<String> = list(xs).iter().map(|item| item.to_string()).collect();
+            Ok(Value::String(items.join(separator)))
+        },
+    },
+    Method {
+        name: "push",
+        arity: 1,
+        mutates: true,
+        run: |xs, mut args, _| {
+            list_mut(xs).push(args.remove(0));
+            Ok(Value::None)
+        },
+    },
+    Method {
+        name: "pop",
+        arity: 0,
+        mutates: true,
+        run: |xs, _, span| match list_mut(xs).pop() {
+            Some(item) => Ok(item),
+            None => Err(runtime_error(
+                "cannot pop from an empty list".to_string(),
+                span,
+            )),
+        },
+    },
+    Method {
+        name: "insert",
+        arity: 2,
+        mutates: true,
+        run: |xs, mut args, span| {
+            let items = list_mut(xs);
+            // any of the gaps around the items, so `len` and -1 append
+            let index = position(&args[0], items.len() + 1, span)?;
+            items.insert(index, args.remove(1));
+            Ok(Value::None)
+        },
+    },
+    Method {
+        name: "remove",
+        arity: 1,
+        mutates: true,
+        run: |xs, args, span| {
+            let items = list_mut(xs);
+            let index = position(&args[0], items.len(), span)?;
+            Ok(items.remove(index))
+        },
+    },
+];
+
+static MAP_METHODS: &[Method] = &[
+    Method {
+        name: "len",
+        arity: 0,
+        mutates: false,
+        run: |m, _, _| Ok(Value::Int(map(m).len() as i64)),
+    },
+    Method {
+        name: "has",
+        arity: 1,
+        mutates: false,
+        run: |m, args, span| {
+            let key = string_arg("has", &args[0], span)?;
+            Ok(Value::Bool(map(m).get(key).is_some()))
+        },
+    },
+    Method {
+        name: "keys",
+        arity: 0,
+        mutates: false,
+        run: |m, _, _| {
+            let keys = map(m)
+                .keys()
+                .map(|key| Value::String(key.clone()))
+                .collect();
+            Ok(Value::List(Rc::new(keys)))
+        },
+    },
+    Method {
+        name: "values",
+        arity: 0,
+        mutates: false,
+        run: |m, _, _| {
+            let values = map(m).iter().map(|(_, value)| value.clone()).collect();
+            Ok(Value::List(Rc::new(values)))
+        },
+    },
+    Method {
+        name: "remove",
+        arity: 1,
+        mutates: true,
+        run: |m, args, span| {
+            let key = string_arg("remove", &args[0], span)?;
+            let map = match m {
+                Value::Map(map) => Rc::make_mut(map),
+                _ => unreachable!("map methods are only looked up for maps"),
+            };
+            Ok(map.remove(key).unwrap_or(Value::None))
+        },
+    },
+];
+
+fn string_arg<'v>(method: &str, arg: &'v Value, span: Span) -> Result<&'v str, RuntimeError> {
+    match arg {
+        Value::String(s) => Ok(s),
+        arg => Err(runtime_error(
+            format!("'{}' expects a string, found {}", method, arg.type_name()),
+            span,
+        )),
+    }
+}
+
+// the receivers below are known to have the right type, methods are looked up by it
+
+fn string(receiver: &Value) -> &str {
+    match receiver {
+        Value::String(s) => s,
+        _ => unreachable!("string methods are only looked up for strings"),
+    }
+}
+
+fn list(receiver: &Value) -> &[Value] {
+    match receiver {
+        Value::List(items) => items,
+        _ => unreachable!("list methods are only looked up for lists"),
+    }
+}
+
+fn list_mut(receiver: &mut Value) -> &mut Vec<Value> {
+    match receiver {
+        Value::List(items) => Rc::make_mut(items),
+        _ => unreachable!("list methods are only looked up for lists"),
+    }
+}
+
+fn map(receiver: &Value) -> &Map {
+    match receiver {
+        Value::Map(map) => map,
+        _ => unreachable!("map methods are only looked up for maps"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use super::*;
+
+    #[test]
+    fn lookup_test() {
+        let s = Value::String("abc".to_string());
+        assert_eq!("len", lookup(&s, "len").unwrap().name);
+        assert!(lookup(&s, "push").is_none());
+        assert!(lookup(&Value::Int(1), "len").is_none());
+
+        assert!(is_mutating("push"));
+        assert!(is_mutating("remove"));
+        assert!(!is_mutating("len"));
+    }
+}
@@ -1,275 +1,1279 @@
-use crate::token;
+use crate::ast::{
+    Argument, BinaryOp, CaseArm, ErrorBinding, ErrorHandler, Expr, FunctionDecl, Literal,
+    LogicalOp, Param, Stmt, TypeAnnotation, UnaryOp,
+};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::lexer;
+use crate::token::{self, Span};
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
 
-pub struct Parser {
-    tokens: Vec<token::Token>,
+pub struct Parser<'a> {
+    tokens: Vec<token::Token<'a>>,
+    trivia: Vec<token::Token<'a>>, // the comments, in source order
+    docs: HashMap<usize, String>,  // doc text by the offset of the def or module it documents
     current_index: usize,
+    errors: Vec<ParseError>,
+    warnings: Vec<Diagnostic>,
+    function_depth: usize, // how many function bodies enclose the current token
+    loop_depth: usize,     // how many loops enclose it within the current function
 }
 
-pub trait Expression {
-    fn name(&self) -> String;
-    fn value(&self) -> String;
+// A syntax error at `span`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub span: Span,
+    pub hint: Option<String>,
 }
 
-pub struct UnaryExpression {
-    token: Option<token::Token>,
-    expr: Option<Box<dyn Expression>>,
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseErrorKind {
+    // what the grammar expected and what was found there instead
+    Unexpected { expected: String, found: String },
+    // a well-formed statement in a place it can't be used, e.g. `return` outside of a function
+    Misplaced(String),
 }
 
-impl Expression for UnaryExpression {
-    fn name(&self) -> String {
-        "unary".to_string()
+impl ParseError {
+    pub fn message(&self) -> String {
+        match &self.kind {
+            ParseErrorKind::Unexpected { expected, found } => {
+                format!("expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::Misplaced(message) => message.clone(),
+        }
     }
 
-    fn value(&self) -> String {
-        let expr_val = match &self.expr {
-            Some(er) => er.value(),
-            None => "".to_string(),
-        };
+    pub fn to_diagnostic(&self, source: &str) -> Diagnostic {
+        let diagnostic = Diagnostic::at_span(Severity::Error, self.message(), self.span, source);
+        match &self.hint {
+            Some(hint) => diagnostic.with_hint(hint),
+            None => diagnostic,
+        }
+    }
+}
 
-        let opr = match self.token {
-            Some(t) => t.val,
-            None => "",
-        };
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at col {}", self.message(), self.span.start + 1)
+    }
+}
+
+type ParseResult<T> = Result<T, ParseError>;
+
+impl<'a> Parser<'a> {
+    // program → ( statement NEWLINE )* EOF ;
+    // Syntax errors don't stop the parser: it skips to the next statement and keeps
+    // going, so that all of them are reported together.
+    pub fn parse(&mut self) -> Result<Vec<Stmt>, Vec<ParseError>> {
+        let mut statements = Vec::new();
 
-        if expr_val == "" && opr == "" {
-            return "val".to_string();
+        self.skip_newlines();
+        while !self.is_at_end() {
+            if self.check_token(&token::TokenType::RightBrace) {
+                let error = self.error_at_current("statement");
+                self.errors.push(error);
+                self.advance_token();
+            } else if let Some(stmt) = self.statement_or_recover() {
+                statements.push(stmt);
+            }
+            self.skip_newlines();
         }
 
-        format!("{} {}", expr_val, opr)
+        if !self.errors.is_empty() {
+            return Err(std::mem::take(&mut self.errors));
+        }
+        Ok(statements)
     }
-}
 
-pub struct BinaryExpression {
-    left: Option<Box<dyn Expression>>,
-    right: Option<Box<dyn Expression>>,
-    token: Option<token::Token>,
-}
+    // Problems that don't stop the program from running, such as a `case` that
+    // misses a boolean value
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
 
-impl Expression for BinaryExpression {
-    fn name(&self) -> String {
-        return "binary".to_string();
+    // All the comments of the source, for tools that need them back such as a formatter
+    #[allow(dead_code)]
+    pub fn trivia(&self) -> &[token::Token<'a>] {
+        &self.trivia
     }
 
-    fn value(&self) -> String {
-        let left_val = match &self.left {
-            Some(l) => l.value(),
-            None => "".to_string(),
+    // Parses a full statement including its terminator. On a syntax error the error is
+    // recorded and the remaining tokens of the statement are skipped.
+    fn statement_or_recover(&mut self) -> Option<Stmt> {
+        let result = match self.statement() {
+            Ok(stmt) => self.end_of_statement().map(|_| stmt),
+            Err(err) => Err(err),
         };
 
-        let right_val = match &self.right {
-            Some(r) => r.value(),
-            None => "".to_string(),
+        match result {
+            Ok(stmt) => Some(stmt),
+            Err(err) => {
+                self.errors.push(err);
+                self.synchronize();
+                None
+            }
+        }
+    }
+
+    // Skips ahead to a statement boundary: past the next newline, or up to the `}`
+    // that closes the enclosing block
+    fn synchronize(&mut self) {
+        while !self.is_at_end() {
+            if self.check_token(&token::TokenType::Newline) {
+                self.advance_token();
+                return;
+            }
+            if self.check_token(&token::TokenType::RightBrace) {
+                return;
+            }
+            self.advance_token();
+        }
+    }
+
+    // statement → printStmt | varDecl | funDecl | returnStmt | forStmt | raiseStmt | "break"
+    //             | "continue" | moduleDecl | importStmt | block | expression ;
+    fn statement(&mut self) -> ParseResult<Stmt> {
+        if self.check_token(&token::TokenType::Print) {
+            return self.print_statement();
+        }
+        if self.match_next_token(&vec![token::TokenType::Def, token::TokenType::Defp]) {
+            return self.function_declaration();
+        }
+        if self.check_token(&token::TokenType::Return) {
+            return self.return_statement();
+        }
+        if self.check_token(&token::TokenType::For) {
+            return self.for_statement();
+        }
+        if self.check_token(&token::TokenType::Module) {
+            return self.module_declaration();
+        }
+        if self.check_token(&token::TokenType::Import) {
+            return self.import_statement();
+        }
+        if self.check_token(&token::TokenType::Raise) {
+            let keyword = self.next_token();
+            self.advance_token();
+            let value = self.expression()?;
+            return Ok(Stmt::Raise {
+                span: keyword.span.to(value.span()),
+                value,
+            });
+        }
+        if self.match_next_token(&vec![token::TokenType::Break, token::TokenType::Continue]) {
+            return Ok(self.loop_control());
+        }
+        if self.match_next_token(&vec![token::TokenType::Let, token::TokenType::Var]) {
+            return self.var_declaration();
+        }
+        // `{ key: ...` can only be a map, any other brace opens a block
+        if self.check_token(&token::TokenType::LeftBrace) && !self.check_map() {
+            let (statements, span) = self.block()?;
+            return Ok(Stmt::Block { statements, span });
+        }
+
+        let expr = self.expression()?;
+        let span = expr.span();
+        Ok(Stmt::Expression { expr, span })
+    }
+
+    // printStmt → "print" ( "(" ( expression ( "," expression )* )? ")" | expression ) ;
+    fn print_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let mut args = Vec::new();
+        let end;
+        if self.check_token(&token::TokenType::LeftParen) {
+            self.advance_token();
+            if !self.check_token(&token::TokenType::RightParen) {
+                args.push(self.expression()?);
+                while self.check_token(&token::TokenType::Comma) {
+                    self.advance_token();
+                    args.push(self.expression()?);
+                }
+            }
+            end = self.consume(&token::TokenType::RightParen, "')' after arguments")?;
+        } else {
+            let expr = self.expression()?;
+            end = self.previous_token();
+            args.push(expr);
+        }
+
+        Ok(Stmt::Print {
+            args,
+            span: keyword.span.to(end.span),
+        })
+    }
+
+    // varDecl → ( "let" | "var" ) IDENTIFIER ( "=" expression )? ;
+    // only `var` may leave out the initializer, it then starts out as none
+    fn var_declaration(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
+        let mutable = keyword.token_type == token::TokenType::Var;
+
+        let name = self.consume(
+            &token::TokenType::Identifier,
+            &format!("variable name after '{}'", keyword.val),
+        )?;
+
+        let initializer = if self.check_token(&token::TokenType::Assign) {
+            self.advance_token();
+            self.expression()?
+        } else if mutable {
+            Expr::Literal {
+                value: Literal::None,
+                span: name.span,
+            }
+        } else {
+            let mut err = self.error_at_current(&format!("'=' after '{}'", name.val));
+            err.hint =
+                Some("let bindings need a value, use var to declare it without one".to_string());
+            return Err(err);
         };
 
-        let opr = match self.token {
-            Some(t) => t.val,
-            None => "",
+        let span = keyword.span.to(initializer.span());
+        Ok(Stmt::Let {
+            name: name.val.to_string(),
+            mutable,
+            initializer,
+            span,
+        })
+    }
+
+    // funDecl → ( "def" | "defp" ) IDENTIFIER ( "(" parameters? ")" )? ( ":" type )? block ;
+    // parameters → param ( "," param )* ;
+    // param → IDENTIFIER ( ":" type )? ;
+    fn function_declaration(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let name = self.consume(
+            &token::TokenType::Identifier,
+            &format!("function name after '{}'", keyword.val),
+        )?;
+
+        let mut params = Vec::new();
+        if self.check_token(&token::TokenType::LeftParen) {
+            self.advance_token();
+            self.skip_newlines();
+            while !self.check_token(&token::TokenType::RightParen) {
+                params.push(self.parameter()?);
+                self.skip_newlines();
+                if !self.check_token(&token::TokenType::Comma) {
+                    break;
+                }
+                self.advance_token();
+                self.skip_newlines();
+            }
+            self.consume(&token::TokenType::RightParen, "')' after parameters")?;
+        }
+
+        let return_type = self.type_annotation()?;
+
+        // loops around the declaration can't be broken out of from its body
+        let loop_depth = std::mem::take(&mut self.loop_depth);
+        self.function_depth += 1;
+        let body = self.block();
+        self.function_depth -= 1;
+        self.loop_depth = loop_depth;
+        let (body, body_span) = body?;
+
+        Ok(Stmt::Function(Rc::new(FunctionDecl {
+            name: name.val.to_string(),
+            params,
+            return_type,
+            body,
+            public: keyword.token_type == token::TokenType::Def,
+            doc: self.docs.remove(&keyword.span.start),
+            span: keyword.span.to(body_span),
+        })))
+    }
+
+    fn parameter(&mut self) -> ParseResult<Param> {
+        let name = self.consume(&token::TokenType::Identifier, "parameter name")?;
+        let type_annotation = self.type_annotation()?;
+
+        let span = match &type_annotation {
+            Some(t) => name.span.to(t.span),
+            None => name.span,
         };
+        Ok(Param {
+            name: name.val.to_string(),
+            type_annotation,
+            span,
+        })
+    }
 
-        if left_val == "" && right_val == "" && opr == "" {
-            return "val".to_string();
+    // type → IDENTIFIER | "none" ;
+    // parsed only when a ":" announces it
+    fn type_annotation(&mut self) -> ParseResult<Option<TypeAnnotation>> {
+        if !self.check_token(&token::TokenType::Colon) {
+            return Ok(None);
         }
+        self.advance_token();
 
-        format!("{} {} {}", left_val, opr, right_val)
+        if self.match_next_token(&vec![token::TokenType::Identifier, token::TokenType::None]) {
+            let token = self.next_token();
+            self.advance_token();
+            return Ok(Some(TypeAnnotation {
+                name: token.val.to_string(),
+                span: token.span,
+            }));
+        }
+        Err(self.error_at_current("type name after ':'"))
     }
-}
 
-pub struct EmptyExpression {}
+    // returnStmt → "return" expression? ;
+    fn return_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
 
-impl Expression for EmptyExpression {
-    fn name(&self) -> String {
-        "empty".to_string()
+        if self.function_depth == 0 {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::Misplaced("'return' outside of a function".to_string()),
+                span: keyword.span,
+                hint: Some("return can only be used inside a def or defp body".to_string()),
+            });
+        }
+
+        if self.is_at_end()
+            || self.check_token(&token::TokenType::Newline)
+            || self.check_token(&token::TokenType::RightBrace)
+        {
+            return Ok(Stmt::Return {
+                value: None,
+                span: keyword.span,
+            });
+        }
+
+        let value = self.expression()?;
+        Ok(Stmt::Return {
+            span: keyword.span.to(value.span()),
+            value: Some(value),
+        })
     }
 
-    fn value(&self) -> String {
-        "".to_string()
+    // moduleDecl → "module" IDENTIFIER block ;
+    fn module_declaration(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let name = self.consume(&token::TokenType::Identifier, "module name after 'module'")?;
+        let (body, body_span) = self.block()?;
+        Ok(Stmt::Module {
+            name: name.val.to_string(),
+            body,
+            doc: self.docs.remove(&keyword.span.start),
+            span: keyword.span.to(body_span),
+        })
     }
-}
 
-pub struct LiteralExpression {
-    token: Option<token::Token>,
-}
+    // importStmt → "import" IDENTIFIER ( "." IDENTIFIER )* ;
+    fn import_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
 
-impl Expression for LiteralExpression {
-    fn name(&self) -> String {
-        return "literal".to_string();
+        let mut end = self.consume(&token::TokenType::Identifier, "module name after 'import'")?;
+        let mut path = vec![end.val.to_string()];
+        while self.check_token(&token::TokenType::Dot) {
+            self.advance_token();
+            end = self.consume(&token::TokenType::Identifier, "module name after '.'")?;
+            path.push(end.val.to_string());
+        }
+        Ok(Stmt::Import {
+            path,
+            span: keyword.span.to(end.span),
+        })
     }
 
-    fn value(&self) -> String {
-        let val = match self.token {
-            Some(t) => t.val,
-            None => "",
+    // forStmt → "for" ( IDENTIFIER | "(" IDENTIFIER "," IDENTIFIER ")" ) "in" expression block ;
+    fn for_statement(&mut self) -> ParseResult<Stmt> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let (variable, index) = if self.check_token(&token::TokenType::LeftParen) {
+            self.advance_token();
+            let variable = self.consume(&token::TokenType::Identifier, "loop variable")?;
+            self.consume(&token::TokenType::Comma, "',' after loop variable")?;
+            let index = self.consume(&token::TokenType::Identifier, "index variable")?;
+            self.consume(&token::TokenType::RightParen, "')' after index variable")?;
+            (variable, Some(index.val.to_string()))
+        } else {
+            let variable = self.consume(&token::TokenType::Identifier, "loop variable")?;
+            (variable, None)
         };
-        return val.to_string();
+
+        self.consume(&token::TokenType::In, "'in' after loop variable")?;
+        let iterable = self.expression()?;
+
+        self.loop_depth += 1;
+        let body = self.block();
+        self.loop_depth -= 1;
+        let (body, body_span) = body?;
+
+        Ok(Stmt::For {
+            variable: variable.val.to_string(),
+            index,
+            iterable,
+            body,
+            span: keyword.span.to(body_span),
+        })
     }
-}
 
-pub struct GroupExpression {
-    expr: Option<Box<dyn Expression>>,
-}
+    fn loop_control(&mut self) -> Stmt {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        if self.loop_depth == 0 {
+            self.errors.push(ParseError {
+                kind: ParseErrorKind::Misplaced(format!("'{}' outside of a loop", keyword.val)),
+                span: keyword.span,
+                hint: Some(format!(
+                    "{} can only be used inside a for body",
+                    keyword.val
+                )),
+            });
+        }
 
-impl Expression for GroupExpression {
-    fn name(&self) -> String {
-        return "group".to_string();
+        match keyword.token_type {
+            token::TokenType::Break => Stmt::Break { span: keyword.span },
+            _ => Stmt::Continue { span: keyword.span },
+        }
     }
 
-    fn value(&self) -> String {
-        let ex = &self.expr;
-        match ex {
-            Some(e) => return e.value(),
-            None => return "".to_string(),
+    // block → "{" ( statement NEWLINE )* "}" ;
+    fn block(&mut self) -> ParseResult<(Vec<Stmt>, Span)> {
+        let open = self.consume(&token::TokenType::LeftBrace, "'{'")?;
+
+        let mut statements = Vec::new();
+        self.skip_newlines();
+        while !self.check_token(&token::TokenType::RightBrace) && !self.is_at_end() {
+            if let Some(stmt) = self.statement_or_recover() {
+                statements.push(stmt);
+            }
+            self.skip_newlines();
         }
+
+        let close = self.consume(&token::TokenType::RightBrace, "'}' after block")?;
+        Ok((statements, open.span.to(close.span)))
     }
-}
 
-impl Parser {
-    pub fn parse(&mut self) -> Box<dyn Expression> {
-        println!("Input tokens from the lexer:");
-        for token in &self.tokens {
-            println!("{:?}", token);
+    // expression -> assignment
+    fn expression(&mut self) -> ParseResult<Expr> {
+        self.assignment()
+    }
+
+    // assignment → target ( "=" | "+=" | "-=" | "*=" | "/=" | "%=" ) assignment | or ;
+    // target → IDENTIFIER ( "[" expression "]" | "." IDENTIFIER )* ;
+    // `x += 1` keeps its operator, so that the target is only evaluated once
+    fn assignment(&mut self) -> ParseResult<Expr> {
+        let expr = self.or()?;
+
+        let oprs = vec![
+            token::TokenType::Assign,
+            token::TokenType::PlusAssign,
+            token::TokenType::MinusAssign,
+            token::TokenType::MultiplyAssign,
+            token::TokenType::DivideAssign,
+            token::TokenType::ModuloAssign,
+        ];
+        if self.match_next_token(&oprs) {
+            let equals = self.next_token();
+            self.advance_token();
+            let value = self.assignment()?;
+
+            if !expr.is_assignment_target() {
+                return Err(error(
+                    "variable or element to assign to".to_string(),
+                    "expression".to_string(),
+                    expr.span().to(equals.span),
+                ));
+            }
+            let operator = BinaryOp::from_compound_assignment(equals.token_type);
+            let span = expr.span().to(value.span());
+
+            return Ok(match expr {
+                Expr::Index { object, index, .. } => Expr::SetIndex {
+                    object,
+                    index,
+                    operator,
+                    value: Box::new(value),
+                    span,
+                },
+                // `m.key = v` is `m["key"] = v`
+                Expr::Get {
+                    object,
+                    name,
+                    span: key_span,
+                } => Expr::SetIndex {
+                    object,
+                    index: Box::new(Expr::Literal {
+                        value: Literal::String(name),
+                        span: key_span,
+                    }),
+                    operator,
+                    value: Box::new(value),
+                    span,
+                },
+                Expr::Variable { name, .. } => Expr::Assign {
+                    name,
+                    operator,
+                    value: Box::new(value),
+                    span,
+                },
+                _ => unreachable!("checked by is_assignment_target()"),
+            });
         }
-        self.expression()
+
+        Ok(expr)
     }
 
-    // expression -> equality
-    fn expression(&mut self) -> Box<dyn Expression> {
-        return self.equality();
+    //or → and ( "||" and )* ;
+    fn or(&mut self) -> ParseResult<Expr> {
+        self.build_logical(token::TokenType::Or, LogicalOp::Or, Parser::and)
     }
 
-    //equality → comparison ( ( "!=" | "==" ) comparison )* ;
-    fn equality(&mut self) -> Box<dyn Expression> {
-        let expr = self.comparision();
+    //and → equality ( "&&" equality )* ;
+    fn and(&mut self) -> ParseResult<Expr> {
+        self.build_logical(token::TokenType::And, LogicalOp::And, Parser::equality)
+    }
 
+    //equality → comparison ( ( "!=" | "==" ) comparison )* ;
+    fn equality(&mut self) -> ParseResult<Expr> {
         let oprs = vec![token::TokenType::Equal, token::TokenType::NotEqual];
-        self.build_expression(expr, &oprs, "comparision")
+        self.build_expression(&oprs, Parser::comparision)
     }
 
-    //comparison → term ( ( ">" | ">=" | "<" | "<=" ) term )* ;
-    fn comparision(&mut self) -> Box<dyn Expression> {
-        let expr = self.term();
-
+    //comparison → range ( ( ">" | ">=" | "<" | "<=" ) range )* ;
+    fn comparision(&mut self) -> ParseResult<Expr> {
         let oprs = vec![
             token::TokenType::GreaterThan,
             token::TokenType::GreaterThanOrEqual,
             token::TokenType::LesserThan,
             token::TokenType::LesserThanOrEqual,
         ];
-        self.build_expression(expr, &oprs, "term")
+        self.build_expression(&oprs, Parser::range)
     }
 
-    //term  → factor ( ( "-" | "+" ) factor )* ;
-    fn term(&mut self) -> Box<dyn Expression> {
-        let expr = self.factor();
+    //range → term ( ".." term )? ;
+    fn range(&mut self) -> ParseResult<Expr> {
+        let start = self.term()?;
+        if !self.check_token(&token::TokenType::DotDot) {
+            return Ok(start);
+        }
+        self.advance_token();
 
-        let oprs = vec![token::TokenType::Minus, token::TokenType::Plus];
-        self.build_expression(expr, &oprs, "factor")
+        let end = self.term()?;
+        let span = start.span().to(end.span());
+        Ok(Expr::Range {
+            start: Box::new(start),
+            end: Box::new(end),
+            span,
+        })
     }
 
-    //factor → unary ( ( "/" | "*" ) unary )* ;
-    fn factor(&mut self) -> Box<dyn Expression> {
-        let expr = self.unary();
+    //term  → factor ( ( "-" | "+" ) factor )* ;
+    fn term(&mut self) -> ParseResult<Expr> {
+        let oprs = vec![token::TokenType::Minus, token::TokenType::Plus];
+        self.build_expression(&oprs, Parser::factor)
+    }
 
-        let oprs = vec![token::TokenType::Divide, token::TokenType::Multiply];
-        self.build_expression(expr, &oprs, "unary")
+    //factor → unary ( ( "/" | "*" | "%" | "~/" ) unary )* ;
+    fn factor(&mut self) -> ParseResult<Expr> {
+        let oprs = vec![
+            token::TokenType::Divide,
+            token::TokenType::Multiply,
+            token::TokenType::Modulo,
+            token::TokenType::IntDivide,
+        ];
+        self.build_expression(&oprs, Parser::unary)
     }
 
-    //unary → ( "!" | "-" ) unary | primary ;
-    fn unary(&mut self) -> Box<dyn Expression> {
-        let oprs = vec![token::TokenType::Minus];
+    //unary → ( "-" | "!" ) unary | power ;
+    fn unary(&mut self) -> ParseResult<Expr> {
+        let oprs = vec![token::TokenType::Minus, token::TokenType::Not];
         if self.match_next_token(&oprs) {
             let opr = self.next_token();
+            self.advance_token();
 
-            let expr = self.unary();
+            let operand = self.unary()?;
+            let span = opr.span.to(operand.span());
 
-            return Box::new(UnaryExpression {
-                expr: Some(expr),
-                token: Some(opr),
+            let operator = match opr.token_type {
+                token::TokenType::Not => UnaryOp::Not,
+                _ => UnaryOp::Negate,
+            };
+            return Ok(Expr::Unary {
+                operator,
+                operand: Box::new(operand),
+                span,
             });
         }
 
-        self.primary()
+        self.power()
     }
 
-    //primary → NUMBER | STRING | "true" | "false" | "nil"  | "(" expression ")" ;
-    fn primary(&mut self) -> Box<dyn Expression> {
-        let oprs = vec![
-            token::TokenType::True,
-            token::TokenType::False,
-            token::TokenType::Number,
-            token::TokenType::String,
-            token::TokenType::Identifier,
-        ];
-        if self.match_next_token(&oprs) {
-            let token = self.next_token();
+    //power → call ( "**" unary )? ;
+    // right associative, and binding tighter than a unary minus on its left: -2 ** 2 is -4
+    fn power(&mut self) -> ParseResult<Expr> {
+        let base = self.call()?;
+        if !self.check_token(&token::TokenType::Power) {
+            return Ok(base);
+        }
+        self.advance_token();
+
+        let exponent = self.unary()?;
+        let span = base.span().to(exponent.span());
+        Ok(Expr::Binary {
+            left: Box::new(base),
+            operator: BinaryOp::Power,
+            right: Box::new(exponent),
+            span,
+        })
+    }
+
+    //call → primary ( "(" arguments? ")" | "." IDENTIFIER | "[" subscript "]" )* ;
+    fn call(&mut self) -> ParseResult<Expr> {
+        let mut expr = self.primary()?;
+
+        loop {
+            if self.check_token(&token::TokenType::Dot) {
+                self.advance_token();
+                let name = self.consume(&token::TokenType::Identifier, "member name after '.'")?;
+                expr = Expr::Get {
+                    span: expr.span().to(name.span),
+                    object: Box::new(expr),
+                    name: name.val.to_string(),
+                };
+                continue;
+            }
+            if self.check_token(&token::TokenType::LeftBracket) {
+                self.advance_token();
+                expr = self.subscript(expr)?;
+                continue;
+            }
+            if !self.check_token(&token::TokenType::LeftParen) {
+                break;
+            }
             self.advance_token();
-            return Box::new(LiteralExpression { token: Some(token) });
+            let args = self.arguments()?;
+            let end = self.consume(&token::TokenType::RightParen, "')' after arguments")?;
+            let span = expr.span().to(end.span);
+
+            expr = Expr::Call {
+                callee: Box::new(expr),
+                args,
+                span,
+            };
+        }
+
+        Ok(expr)
+    }
+
+    //subscript → expression | expression? ":" expression? ;
+    fn subscript(&mut self, object: Expr) -> ParseResult<Expr> {
+        if self.check_token(&token::TokenType::Colon) {
+            return self.slice(object, None);
+        }
+        let index = self.expression()?;
+        if self.check_token(&token::TokenType::Colon) {
+            return self.slice(object, Some(index));
         }
-        if self.match_next_token(&vec![token::TokenType::LeftParen]) {
+
+        let end = self.consume(&token::TokenType::RightBracket, "']' after index")?;
+        Ok(Expr::Index {
+            span: object.span().to(end.span),
+            object: Box::new(object),
+            index: Box::new(index),
+        })
+    }
+
+    fn slice(&mut self, object: Expr, start: Option<Expr>) -> ParseResult<Expr> {
+        self.advance_token();
+        let end = if self.check_token(&token::TokenType::RightBracket) {
+            None
+        } else {
+            Some(self.expression()?)
+        };
+
+        let close = self.consume(&token::TokenType::RightBracket, "']' after slice")?;
+        Ok(Expr::Slice {
+            span: object.span().to(close.span),
+            object: Box::new(object),
+            start: start.map(Box::new),
+            end: end.map(Box::new),
+        })
+    }
+
+    //arguments → argument ( "," argument )* ;
+    //argument → ( IDENTIFIER ":" )? expression ;
+    fn arguments(&mut self) -> ParseResult<Vec<Argument>> {
+        let mut args: Vec<Argument> = Vec::new();
+        self.skip_newlines();
+
+        while !self.check_token(&token::TokenType::RightParen) {
+            let named = self.check_token(&token::TokenType::Identifier)
+                && self.check_token_after(&token::TokenType::Colon);
+
+            let arg = if named {
+                let name = self.next_token();
+                self.advance_token();
+                self.advance_token();
+                let value = self.expression()?;
+                Argument {
+                    name: Some(name.val.to_string()),
+                    span: name.span.to(value.span()),
+                    value,
+                }
+            } else {
+                let value = self.expression()?;
+                if args.iter().any(|arg| arg.name.is_some()) {
+                    let mut err = error(
+                        "named argument".to_string(),
+                        "positional argument".to_string(),
+                        value.span(),
+                    );
+                    err.hint = Some("positional arguments must come before named ones".to_string());
+                    return Err(err);
+                }
+                Argument {
+                    name: None,
+                    span: value.span(),
+                    value,
+                }
+            };
+            args.push(arg);
+
+            self.skip_newlines();
+            if !self.check_token(&token::TokenType::Comma) {
+                break;
+            }
             self.advance_token();
+            self.skip_newlines();
+        }
+
+        Ok(args)
+    }
+
+    //primary → INT | FLOAT | BYTE | STRING | IDENTIFIER | "true" | "false" | "none"  | "(" expression ")" | ifExpr | caseExpr
+    //        | "error" "(" arguments? ")" | handleExpr ;
+    fn primary(&mut self) -> ParseResult<Expr> {
+        let token = self.next_token();
+        let span = token.span;
+
+        let value = match token.token_type {
+            token::TokenType::True => Literal::Bool(true),
+            token::TokenType::False => Literal::Bool(false),
+            token::TokenType::None => Literal::None,
+            token::TokenType::String | token::TokenType::RawString => {
+                Literal::String(string_value(&token))
+            }
+            // the lexer only lets valid numbers through
+            token::TokenType::Int => match lexer::int_value(token.val) {
+                Some(n) => Literal::Int(n),
+                None => return Err(self.error_at_current("number")),
+            },
+            token::TokenType::Float => match lexer::float_value(token.val) {
+                Some(n) => Literal::Float(n),
+                None => return Err(self.error_at_current("number")),
+            },
+            token::TokenType::Byte => match lexer::byte_value(token.val) {
+                Some(byte) => Literal::Int(byte as i64),
+                None => return Err(self.error_at_current("byte")),
+            },
+            token::TokenType::Identifier => {
+                self.advance_token();
+                return Ok(Expr::Variable {
+                    name: token.val.to_string(),
+                    span,
+                });
+            }
+            token::TokenType::StringStart => return self.interpolation(),
+            token::TokenType::LeftBracket => return self.list(),
+            token::TokenType::LeftBrace => return self.map(),
+            token::TokenType::If => return self.if_expression(),
+            token::TokenType::Case => return self.case_expression(),
+            token::TokenType::Handle => return self.handle_expression(),
+            token::TokenType::Error => {
+                self.advance_token();
+                self.consume(&token::TokenType::LeftParen, "'(' after 'error'")?;
+                let args = self.arguments()?;
+                let end = self.consume(&token::TokenType::RightParen, "')' after arguments")?;
+                return Ok(Expr::Error {
+                    args,
+                    span: span.to(end.span),
+                });
+            }
+            token::TokenType::LeftParen => {
+                self.advance_token();
+
+                let expr = self.expression()?;
+                let end = self.consume(&token::TokenType::RightParen, "')' after expression")?;
+                return Ok(Expr::Grouping {
+                    expr: Box::new(expr),
+                    span: span.to(end.span),
+                });
+            }
+            _ => return Err(self.error_at_current("expression")),
+        };
+
+        self.advance_token();
+        Ok(Expr::Literal { value, span })
+    }
+
+    //interpolation → STRING_START expression ( STRING_MIDDLE expression )* STRING_END ;
+    fn interpolation(&mut self) -> ParseResult<Expr> {
+        let start = self.next_token();
+        self.advance_token();
+        let mut parts = Vec::new();
+        let mut text = start;
+
+        loop {
+            let value = string_value(&text);
+            if !value.is_empty() {
+                parts.push(Expr::Literal {
+                    value: Literal::String(value),
+                    span: text.span,
+                });
+            }
+            if text.token_type == token::TokenType::StringEnd {
+                break;
+            }
+
+            self.skip_newlines();
+            parts.push(self.expression()?);
+            self.skip_newlines();
+            if !self.match_next_token(&vec![
+                token::TokenType::StringMiddle,
+                token::TokenType::StringEnd,
+            ]) {
+                return Err(self.error_at_current("'}' after embedded expression"));
+            }
+            text = self.next_token();
+            self.advance_token();
+        }
+
+        Ok(Expr::Interpolation {
+            parts,
+            span: start.span.to(text.span),
+        })
+    }
+
+    //list → "[" ( expression ( "," expression )* ","? )? "]" ;
+    fn list(&mut self) -> ParseResult<Expr> {
+        let open = self.next_token();
+        self.advance_token();
+        let mut elements = Vec::new();
+        self.skip_newlines();
+
+        while !self.check_token(&token::TokenType::RightBracket) {
+            elements.push(self.expression()?);
+            self.skip_newlines();
+            if !self.check_token(&token::TokenType::Comma) {
+                break;
+            }
+            self.advance_token();
+            self.skip_newlines();
+        }
+
+        let close = self.consume(&token::TokenType::RightBracket, "']' after list elements")?;
+        Ok(Expr::List {
+            elements,
+            span: open.span.to(close.span),
+        })
+    }
+
+    //map → "{" ( entry ( "," entry )* ","? )? "}" ;
+    //entry → ( STRING | IDENTIFIER ) ":" expression ;
+    fn map(&mut self) -> ParseResult<Expr> {
+        let open = self.next_token();
+        self.advance_token();
+        let mut entries = Vec::new();
+        self.skip_newlines();
+
+        while !self.check_token(&token::TokenType::RightBrace) {
+            let key = self.next_token();
+            if !matches!(
+                key.token_type,
+                token::TokenType::String | token::TokenType::Identifier
+            ) {
+                return Err(self.error_at_current("map key"));
+            }
+            self.advance_token();
+            self.consume(&token::TokenType::Colon, "':' after map key")?;
+            entries.push((string_value(&key), self.expression()?));
+
+            self.skip_newlines();
+            if !self.check_token(&token::TokenType::Comma) {
+                break;
+            }
+            self.advance_token();
+            self.skip_newlines();
+        }
+
+        let close = self.consume(&token::TokenType::RightBrace, "'}' after map entries")?;
+        Ok(Expr::Map {
+            entries,
+            span: open.span.to(close.span),
+        })
+    }
+
+    //ifExpr → "if" expression ","? block ( "elsif" expression ","? block )* ( "else" block )? ;
+    fn if_expression(&mut self) -> ParseResult<Expr> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let condition = self.expression()?;
+        // the one-liner form `if (a == b), { ... }`
+        if self.check_token(&token::TokenType::Comma) {
+            self.advance_token();
+        }
+        let (then_branch, mut span) = self.block()?;
+        span = keyword.span.to(span);
+
+        let else_branch = if self.check_else(&token::TokenType::ElsIf) {
+            let elsif = self.if_expression()?;
+            span = span.to(elsif.span());
+            Some(vec![Stmt::Expression {
+                span: elsif.span(),
+                expr: elsif,
+            }])
+        } else if self.check_else(&token::TokenType::Else) {
+            self.advance_token();
+            let (else_branch, else_span) = self.block()?;
+            span = span.to(else_span);
+            Some(else_branch)
+        } else {
+            None
+        };
+
+        Ok(Expr::If {
+            condition: Box::new(condition),
+            then_branch,
+            else_branch,
+            span,
+        })
+    }
+
+    //handleExpr → "handle" block ( "error" STRING? errorBinding? block )+ ;
+    //errorBinding → IDENTIFIER | "(" IDENTIFIER ( "," IDENTIFIER )* ")" ;
+    // Each `error` clause has to start on the line of the preceding `}`, otherwise it
+    // would be taken for an `error(...)` expression on the next line.
+    fn handle_expression(&mut self) -> ParseResult<Expr> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let (body, mut span) = self.block()?;
+        span = keyword.span.to(span);
+
+        let mut handlers = Vec::new();
+        loop {
+            let start = self.consume(
+                &token::TokenType::Error,
+                "'error' clause after handle block",
+            )?;
 
-            let expr = self.expression();
-            if self.check_token(&token::TokenType::RightParen) {
+            let mut kind = None;
+            if self.check_token(&token::TokenType::String) {
+                kind = Some(string_value(&self.next_token()));
                 self.advance_token();
-                return Box::new(GroupExpression { expr: Some(expr) });
+            }
+
+            let binding = if self.check_token(&token::TokenType::Identifier) {
+                let name = self.next_token();
+                self.advance_token();
+                Some(ErrorBinding::Value(name.val.to_string()))
+            } else if self.check_token(&token::TokenType::LeftParen) {
+                Some(ErrorBinding::Fields(self.error_fields()?))
+            } else {
+                None
+            };
+
+            let (handler_body, end) = self.block()?;
+            span = span.to(end);
+            handlers.push(ErrorHandler {
+                kind,
+                binding,
+                body: handler_body,
+                span: start.span.to(end),
+            });
+
+            if !self.check_token(&token::TokenType::Error) {
+                break;
+            }
+        }
+
+        Ok(Expr::Handle {
+            body,
+            handlers,
+            span,
+        })
+    }
+
+    // `(message, data, trace)`, trailing names may be left out
+    fn error_fields(&mut self) -> ParseResult<Vec<String>> {
+        let open = self.next_token();
+        self.advance_token();
+
+        let mut names =
+            vec![self.consume(&token::TokenType::Identifier, "name of the error message")?];
+        while self.check_token(&token::TokenType::Comma) {
+            self.advance_token();
+            names.push(self.consume(&token::TokenType::Identifier, "name of an error field")?);
+        }
+        let close = self.consume(&token::TokenType::RightParen, "')' after error fields")?;
+
+        if names.len() > 3 {
+            let mut err = error(
+                "at most 3 names".to_string(),
+                format!("{}", names.len()),
+                open.span.to(close.span),
+            );
+            err.hint = Some("an error destructures into (message, data, trace)".to_string());
+            return Err(err);
+        }
+        Ok(names.iter().map(|name| name.val.to_string()).collect())
+    }
+
+    //caseExpr → "case" expression "{" ( caseArm ( "," | NEWLINE )* )* "}" ;
+    //caseArm → ( pattern ( "," pattern )* | "none" ) ":" ( block | expression ) ;
+    //pattern → "-"? ( INT | FLOAT | BYTE ) | STRING | "true" | "false" ;
+    fn case_expression(&mut self) -> ParseResult<Expr> {
+        let keyword = self.next_token();
+        self.advance_token();
+
+        let subject = self.expression()?;
+        self.consume(&token::TokenType::LeftBrace, "'{' after case subject")?;
+
+        let mut arms = Vec::new();
+        let mut default = None;
+        self.skip_separators();
+        while !self.check_token(&token::TokenType::RightBrace) && !self.is_at_end() {
+            let start = self.next_token();
+            if start.token_type == token::TokenType::None {
+                self.advance_token();
+                self.consume(&token::TokenType::Colon, "':' after 'none'")?;
+                let (body, _) = self.case_body()?;
+                if default.is_some() {
+                    return Err(error(
+                        "case arm".to_string(),
+                        "a second 'none' arm".to_string(),
+                        start.span,
+                    ));
+                }
+                default = Some(body);
             } else {
-                panic!("Invalid group expression");
+                let mut patterns = vec![self.pattern()?];
+                while self.check_token(&token::TokenType::Comma) {
+                    self.advance_token();
+                    patterns.push(self.pattern()?);
+                }
+                self.consume(&token::TokenType::Colon, "':' after case pattern")?;
+                let (body, end) = self.case_body()?;
+                arms.push(CaseArm {
+                    patterns,
+                    body,
+                    span: start.span.to(end),
+                });
             }
+            self.skip_separators();
         }
+        let close = self.consume(&token::TokenType::RightBrace, "'}' after case arms")?;
 
-        return Box::new(EmptyExpression {});
+        if default.is_none() {
+            self.check_bool_exhaustiveness(&keyword, &arms);
+        }
+
+        Ok(Expr::Case {
+            subject: Box::new(subject),
+            arms,
+            default,
+            span: keyword.span.to(close.span),
+        })
+    }
+
+    fn pattern(&mut self) -> ParseResult<Expr> {
+        let pattern = self.unary()?;
+        match &pattern {
+            Expr::Literal { .. } => Ok(pattern),
+            Expr::Unary { operand, .. } if matches!(**operand, Expr::Literal { .. }) => Ok(pattern),
+            _ => {
+                let mut err = error(
+                    "literal pattern".to_string(),
+                    format!("'{}'", pattern),
+                    pattern.span(),
+                );
+                err.hint = Some("case arms match numbers, strings and booleans".to_string());
+                Err(err)
+            }
+        }
+    }
+
+    fn case_body(&mut self) -> ParseResult<(Vec<Stmt>, Span)> {
+        if self.check_token(&token::TokenType::LeftBrace) {
+            return self.block();
+        }
+        let expr = self.expression()?;
+        let span = expr.span();
+        Ok((vec![Stmt::Expression { expr, span }], span))
+    }
+
+    fn skip_separators(&mut self) {
+        while self.match_next_token(&vec![token::TokenType::Comma, token::TokenType::Newline]) {
+            self.advance_token();
+        }
+    }
+
+    // A case whose arms are all booleans but that misses one of them and has no
+    // `none` fallback evaluates to none for the missing value, which is rarely intended
+    fn check_bool_exhaustiveness(&mut self, keyword: &token::Token, arms: &[CaseArm]) {
+        let mut seen = Vec::new();
+        for pattern in arms.iter().flat_map(|arm| &arm.patterns) {
+            match pattern {
+                Expr::Literal {
+                    value: Literal::Bool(b),
+                    ..
+                } => seen.push(*b),
+                _ => return,
+            }
+        }
+
+        if let Some(missing) = [true, false].iter().find(|b| !seen.contains(b)) {
+            let warning = Diagnostic::warning(
+                format!("case on a bool does not handle '{}'", missing),
+                keyword.line,
+                keyword.column,
+                keyword.span,
+            )
+            .with_hint(&format!("add a '{}' arm or a 'none' fallback arm", missing));
+            self.warnings.push(warning);
+        }
+    }
+
+    // `else` and `elsif` may start on the line after the closing brace; the
+    // newlines are only skipped when one of them follows
+    fn check_else(&mut self, token_type: &token::TokenType) -> bool {
+        let index = self.current_index;
+        self.skip_newlines();
+        if self.check_token(token_type) {
+            return true;
+        }
+        self.current_index = index;
+        false
+    }
+
+    fn build_logical(
+        &mut self,
+        token_type: token::TokenType,
+        operator: LogicalOp,
+        operand: fn(&mut Parser<'a>) -> ParseResult<Expr>,
+    ) -> ParseResult<Expr> {
+        let mut expr = operand(self)?;
+
+        while self.check_token(&token_type) {
+            self.advance_token();
+            let right = operand(self)?;
+            let span = expr.span().to(right.span());
+
+            expr = Expr::Logical {
+                left: Box::new(expr),
+                operator,
+                right: Box::new(right),
+                span,
+            };
+        }
+
+        Ok(expr)
     }
 
     fn build_expression(
         &mut self,
-        expr: Box<dyn Expression>,
         oprs: &Vec<token::TokenType>,
-        right_expr_type: &str,
-    ) -> Box<dyn Expression> {
-        let mut final_expr = expr;
+        operand: fn(&mut Parser<'a>) -> ParseResult<Expr>,
+    ) -> ParseResult<Expr> {
+        let mut final_expr = operand(self)?;
 
         while self.match_next_token(oprs) {
-            println!("found expression type {}", right_expr_type);
-
             let operator = self.next_token();
-
             self.advance_token();
 
-            let mut right: Box<dyn Expression> = Box::new(EmptyExpression {});
-            match right_expr_type {
-                "comparision" => {
-                    right = self.comparision();
-                }
-                "term" => {
-                    right = self.term();
-                }
-                "factor" => {
-                    right = self.factor();
-                }
-                "unary" => {
-                    right = self.unary();
-                }
-                _ => println!("not supported"),
-            }
+            let right = operand(self)?;
+            let span = final_expr.span().to(right.span());
 
-            final_expr = Box::new(BinaryExpression {
-                left: Some(final_expr),
-                right: Some(right),
-                token: Some(operator),
-            });
+            final_expr = Expr::Binary {
+                left: Box::new(final_expr),
+                operator: BinaryOp::from_token_type(operator.token_type)
+                    .expect("binary operator token"),
+                right: Box::new(right),
+                span,
+            };
         }
 
-        final_expr
+        Ok(final_expr)
+    }
+
+    // statements end at a newline, at the end of input or right before the `}` closing a block
+    fn end_of_statement(&mut self) -> ParseResult<()> {
+        if self.is_at_end()
+            || self.check_token(&token::TokenType::Newline)
+            || self.check_token(&token::TokenType::RightBrace)
+        {
+            return Ok(());
+        }
+        Err(self.error_at_current("end of statement"))
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.check_token(&token::TokenType::Newline) {
+            self.advance_token();
+        }
+    }
+
+    fn consume(
+        &mut self,
+        token_type: &token::TokenType,
+        expected: &str,
+    ) -> ParseResult<token::Token<'a>> {
+        if self.check_token(token_type) {
+            let token = self.next_token();
+            self.advance_token();
+            return Ok(token);
+        }
+        Err(self.error_at_current(expected))
+    }
+
+    fn error_at_current(&mut self, expected: &str) -> ParseError {
+        let token = self.next_token();
+        error(expected.to_string(), describe(&token), token.span)
+    }
+
+    fn is_at_end(&self) -> bool {
+        match self.tokens.get(self.current_index) {
+            Some(t) => t.token_type == token::TokenType::EndOfFile,
+            None => true,
+        }
     }
 
     fn advance_token(&mut self) {
-        self.current_index = self.current_index + 1;
-        println!("Next token index: {}", self.current_index);
+        self.current_index += 1;
     }
 
-    fn next_token(&mut self) -> token::Token {
+    fn next_token(&mut self) -> token::Token<'a> {
         let token = self.tokens.get(self.current_index);
         match token {
-            Some(t) => return *t,
+            Some(t) => *t,
+            None => token::new_illegal_token(),
+        }
+    }
+
+    fn previous_token(&self) -> token::Token<'a> {
+        match self.current_index.checked_sub(1) {
+            Some(index) => self.tokens[index],
             None => token::new_illegal_token(),
         }
     }
@@ -281,7 +1285,34 @@ impl Parser {
             }
         }
 
-        return false;
+        false
+    }
+
+    // In statement position a '{' opens a map only when its first entry follows,
+    // i.e. a string or identifier key and a ':' (newlines in between are skipped).
+    // Anything else, `{}` included, opens a block.
+    fn check_map(&self) -> bool {
+        let mut rest = self.tokens[self.current_index + 1..]
+            .iter()
+            .filter(|t| t.token_type != token::TokenType::Newline);
+        matches!(
+            (
+                rest.next().map(|t| t.token_type),
+                rest.next().map(|t| t.token_type)
+            ),
+            (
+                Some(token::TokenType::String | token::TokenType::Identifier),
+                Some(token::TokenType::Colon)
+            )
+        )
+    }
+
+    // like check_token, but looks one token past the current one
+    fn check_token_after(&self, token_type: &token::TokenType) -> bool {
+        match self.tokens.get(self.current_index + 1) {
+            Some(t) => t.token_type == *token_type,
+            None => false,
+        }
     }
 
     fn check_token(&mut self, next_token: &token::TokenType) -> bool {
@@ -298,14 +1329,110 @@ impl Parser {
             None => return false,
         }
 
-        return false;
+        false
     }
 }
 
-pub fn new(tokens: Vec<token::Token>) -> Parser {
+fn error(expected: String, found: String, span: Span) -> ParseError {
+    ParseError {
+        kind: ParseErrorKind::Unexpected { expected, found },
+        span,
+        hint: None,
+    }
+}
+
+// the text of a string token or string part, or the name of an identifier
+fn string_value(token: &token::Token) -> String {
+    match token.token_type {
+        token::TokenType::Identifier | token::TokenType::RawString => token.val.to_string(),
+        _ => lexer::unescape(token.val),
+    }
+}
+
+// how a token is called in error messages
+fn describe(token: &token::Token) -> String {
+    match token.token_type {
+        token::TokenType::EndOfFile => "end of input".to_string(),
+        token::TokenType::Newline => "newline".to_string(),
+        token::TokenType::String | token::TokenType::RawString => format!("\"{}\"", token.val),
+        // these parts begin with the '}' closing an embedded expression
+        token::TokenType::StringMiddle | token::TokenType::StringEnd => "'}'".to_string(),
+        _ => format!("'{}'", token.val),
+    }
+}
+
+// Groups the `///` comments on consecutive lines and attaches each group to the
+// def, defp or module starting on the next line. Groups followed by anything
+// else are reported as warnings.
+fn attach_docs(
+    tokens: &[token::Token],
+    trivia: &[token::Token],
+) -> (HashMap<usize, String>, Vec<Diagnostic>) {
+    let mut docs = HashMap::new();
+    let mut warnings = Vec::new();
+
+    let comments: Vec<&token::Token> = trivia
+        .iter()
+        .filter(|t| t.token_type == token::TokenType::DocComment)
+        .collect();
+    let mut groups: Vec<Vec<token::Token>> = Vec::new();
+    for comment in comments {
+        match groups.last_mut() {
+            Some(group) if group.last().unwrap().line + 1 == comment.line => group.push(*comment),
+            _ => groups.push(vec![*comment]),
+        }
+    }
+
+    for group in groups {
+        let last = group.last().unwrap();
+        let next = tokens
+            .iter()
+            .find(|t| t.span.start > last.span.start && t.token_type != token::TokenType::Newline);
+        match next {
+            Some(t)
+                if t.line == last.line + 1
+                    && matches!(
+                        t.token_type,
+                        token::TokenType::Def | token::TokenType::Defp | token::TokenType::Module
+                    ) =>
+            {
+                docs.insert(t.span.start, lexer::doc_text(&group));
+            }
+            _ => warnings.push(
+                Diagnostic::warning(
+                    "doc comment is not followed by a def or module".to_string(),
+                    group[0].line,
+                    group[0].column,
+                    group[0].span.to(last.span),
+                )
+                .with_hint("use '//' for a plain comment"),
+            ),
+        }
+    }
+    (docs, warnings)
+}
+
+pub fn new(tokens: Vec<token::Token<'_>>) -> Parser<'_> {
+    // comments carry no meaning for the grammar, they are kept aside as trivia
+    let (trivia, tokens): (Vec<_>, Vec<_>) = tokens.into_iter().partition(|t| {
+        matches!(
+            t.token_type,
+            token::TokenType::Comment
+                | token::TokenType::BlockComment
+                | token::TokenType::DocComment
+        )
+    });
+    let (docs, warnings) = attach_docs(&tokens, &trivia);
+
     Parser {
         tokens,
+        trivia,
+        docs,
         current_index: 0,
+        errors: Vec::new(),
+        warnings,
+        function_depth: 0,
+        loop_depth: 0,
     }
 }
 
@@ -316,15 +1443,498 @@ mod tests {
     use crate::lexer;
     use crate::parser;
 
-    #[test]
-    fn parser_expression_test() {
-        let input = String::from("(x * y) + 5");
+    fn parse(input: &str) -> Result<Vec<Stmt>, Vec<ParseError>> {
         let mut lexer = lexer::new(input);
-        let tokens = lexer.parse();
+        let tokens = lexer.parse().unwrap();
 
         let mut parser: Parser = parser::new(tokens);
-        let expr = parser.parse();
+        parser.parse()
+    }
+
+    fn parse_error(input: &str) -> ParseError {
+        parse(input).unwrap_err().remove(0)
+    }
+
+    fn parse_expr(input: &str) -> Expr {
+        match parse(input).unwrap().remove(0) {
+            Stmt::Expression { expr, .. } => expr,
+            stmt => panic!("expected an expression statement, found {}", stmt),
+        }
+    }
+
+    #[test]
+    fn parser_expression_test() {
+        let expr = parse_expr("(x * y) + 5");
+        assert_eq!("(+ (group (* x y)) 5)", expr.to_string());
+        assert_eq!(Span::new(0, 11), expr.span());
+    }
+
+    #[test]
+    fn precedence_test() {
+        assert_eq!(
+            "(== (+ 1 (* 2 3)) (- 7))",
+            parse_expr("1 + 2 * 3 == -7").to_string()
+        );
+        assert_eq!("(- (- 10 6) 3)", parse_expr("10 - 6 - 3").to_string());
+        assert_eq!("(< a \"b\")", parse_expr("a < \"b\"").to_string());
+    }
+
+    #[test]
+    fn statements_test() {
+        let stmts = parse("1 + 2\n\nprint(\"a\", 3)\nprint x\n").unwrap();
+        assert_eq!(3, stmts.len());
+        assert_eq!("(print \"a\" 3)", stmts[1].to_string());
+        assert_eq!(Span::new(7, 20), stmts[1].span());
+        assert_eq!("(print x)", stmts[2].to_string());
+    }
+
+    #[test]
+    fn doc_comment_test() {
+        let input = "/// Adds two numbers.\n///\n/// Works on ints.\ndef add(a, b) { a + b }\n\n/// Helpers\nmodule m {\n  // plain\n  defp f() { 1 }\n}";
+        let statements = parse(input).unwrap();
+        match &statements[0] {
+            Stmt::Function(decl) => assert_eq!(
+                Some("Adds two numbers.\n\nWorks on ints.".to_string()),
+                decl.doc
+            ),
+            stmt => panic!("expected a function, found {}", stmt),
+        }
+        match &statements[1] {
+            Stmt::Module { doc, body, .. } => {
+                assert_eq!(Some("Helpers".to_string()), *doc);
+                assert!(matches!(&body[0], Stmt::Function(decl) if decl.doc.is_none()));
+            }
+            stmt => panic!("expected a module, found {}", stmt),
+        }
+
+        let tokens = lexer::new("/// stray\nlet x = 1 /* kept */\n")
+            .parse()
+            .unwrap();
+        let mut parser = parser::new(tokens);
+        assert!(parser.parse().is_ok());
+        assert_eq!(1, parser.warnings().len());
+        assert_eq!(
+            "doc comment is not followed by a def or module",
+            parser.warnings()[0].message
+        );
+        let trivia: Vec<&str> = parser.trivia().iter().map(|t| t.val).collect();
+        assert_eq!(vec!["/// stray", "/* kept */"], trivia);
+    }
+
+    #[test]
+    fn declaration_test() {
+        let stmts = parse("let x = 1\nvar y\ny = x = 2\n{ var x = 3\n x }").unwrap();
+        assert_eq!(4, stmts.len());
+        assert_eq!("(let x 1)", stmts[0].to_string());
+        assert_eq!("(var y none)", stmts[1].to_string());
+        assert_eq!("(= y (= x 2))", stmts[2].to_string());
+        assert_eq!("(block (var x 3) x)", stmts[3].to_string());
+
+        let err = parse_error("let = 3");
+        assert_eq!(
+            "expected variable name after 'let', found '='",
+            err.message()
+        );
+
+        let err = parse_error("let x");
+        assert_eq!("expected '=' after 'x', found end of input", err.message());
+        assert!(err.hint.unwrap().contains("let bindings need a value"));
+
+        let err = parse_error("1 + 2 = 3");
+        assert_eq!(
+            "expected variable or element to assign to, found expression",
+            err.message()
+        );
+        assert_eq!(Span::new(0, 7), err.span);
+
+        let err = parse_error("{ 1");
+        assert_eq!(
+            "expected '}' after block, found end of input",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn function_test() {
+        let stmts = parse(
+            "def print_hello {\n    print(\"hello rusty\")\n}\n\ndef add_two(a: int, b: int): int {\n    a + b\n}\ndefp helper(x) { return x * 2 }",
+        )
+        .unwrap();
+        assert_eq!(3, stmts.len());
+        assert_eq!(
+            "(def print_hello () (print \"hello rusty\"))",
+            stmts[0].to_string()
+        );
+        assert_eq!(
+            "(def add_two (a: int, b: int): int (+ a b))",
+            stmts[1].to_string()
+        );
+        assert_eq!("(defp helper (x) (return (* x 2)))", stmts[2].to_string());
+
+        match &stmts[1] {
+            Stmt::Function(decl) => {
+                assert!(decl.public);
+                assert_eq!(
+                    Some("int"),
+                    decl.return_type.as_ref().map(|t| t.name.as_str())
+                );
+                assert_eq!(Span::new(46, 92), decl.span);
+            }
+            stmt => panic!("expected a function, found {}", stmt),
+        }
+
+        let err = parse_error("def (a) { a }");
+        assert_eq!(
+            "expected function name after 'def', found '('",
+            err.message()
+        );
+
+        let err = parse_error("def f(a:) { a }");
+        assert_eq!("expected type name after ':', found ')'", err.message());
+
+        let err = parse_error("return 1");
+        assert_eq!("'return' outside of a function", err.message());
+    }
+
+    #[test]
+    fn arithmetic_operators_test() {
+        assert_eq!("(+ 1 (% 7 3))", parse_expr("1 + 7 % 3").to_string());
+        assert_eq!("(* (~/ 7 2) 3)", parse_expr("7 ~/ 2 * 3").to_string());
+        assert_eq!("(** 2 (** 3 2))", parse_expr("2 ** 3 ** 2").to_string());
+        assert_eq!("(- (** 2 2))", parse_expr("-2 ** 2").to_string());
+        assert_eq!("(** 2 (- 1))", parse_expr("2 ** -1").to_string());
+        assert_eq!("(* 2 (** x 2))", parse_expr("2 * x ** 2").to_string());
+
+        assert_eq!("(+= x 1)", parse_expr("x += 1").to_string());
+        assert_eq!("(%= x (+ 2 1))", parse_expr("x %= 2 + 1").to_string());
+        assert_eq!(Span::new(0, 6), parse_expr("x -= 1").span());
+
+        let err = parse_error("1 += 2");
+        assert_eq!(
+            "expected variable or element to assign to, found expression",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn list_test() {
+        assert_eq!("(list)", parse_expr("[]").to_string());
+        assert_eq!(
+            "(list 1 (+ 2 3) a)",
+            parse_expr("[1, 2 + 3, a]").to_string()
+        );
+        assert_eq!(
+            "(list (list 1) (list 2))",
+            parse_expr("[\n  [1],\n  [2],\n]").to_string()
+        );
+        assert_eq!(
+            "(index (index grid 0) (- 1))",
+            parse_expr("grid[0][-1]").to_string()
+        );
+        assert_eq!("(slice xs 1 3)", parse_expr("xs[1:3]").to_string());
+        assert_eq!("(slice xs _ 2)", parse_expr("xs[:2]").to_string());
+        assert_eq!("(slice xs 1 _)", parse_expr("xs[1:]").to_string());
+        assert_eq!(Span::new(0, 7), parse_expr("xs[1:3]").span());
+
+        assert_eq!("(= (index xs 0) 5)", parse_expr("xs[0] = 5").to_string());
+        assert_eq!(
+            "(+= (index (index grid i) 1) 1)",
+            parse_expr("grid[i][1] += 1").to_string()
+        );
+
+        let err = parse_error("xs[1:2] = 5");
+        assert_eq!(
+            "expected variable or element to assign to, found expression",
+            err.message()
+        );
+        let err = parse_error("[1, 2");
+        assert_eq!(
+            "expected ']' after list elements, found end of input",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn string_literal_test() {
+        let expr = parse_expr("\"a\\tb\" + r\"a\\tb\"");
+        assert_eq!("(+ \"a\\tb\" \"a\\\\tb\")", expr.to_string());
+        assert_eq!(
+            "(map (\"new\\nline\" 1))",
+            parse_expr("{ \"new\\nline\": 1 }").to_string()
+        );
+    }
+
+    #[test]
+    fn interpolation_test() {
+        assert_eq!(
+            "(str \"total: \" (+ a b) \"!\\n\")",
+            parse_expr("\"total: #{a + b}!\\n\"").to_string()
+        );
+        assert_eq!(
+            "(str (str \"<\" x \">\"))",
+            parse_expr("\"#{\"<#{x}>\"}\"").to_string()
+        );
+        assert_eq!(Span::new(0, 8), parse_expr("\"a #{b}\"").span());
+
+        let err = parse_error("\"a #{}\"");
+        assert_eq!("expected expression, found '}'", err.message());
+        let err = parse_error("\"a #{b c}\"");
+        assert_eq!(
+            "expected '}' after embedded expression, found 'c'",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn map_test() {
+        assert_eq!(
+            "(map (\"a\" 1) (\"b\" (+ 1 1)))",
+            parse_expr("{ \"a\": 1, b: 1 + 1 }").to_string()
+        );
+        assert_eq!(
+            "(map (\"a\" (list)))",
+            parse_expr("{\n  a: [],\n}").to_string()
+        );
+        assert_eq!(
+            "(. (index m \"a\") b)",
+            parse_expr("m[\"a\"].b").to_string()
+        );
+        assert_eq!("(= (index m \"b\") 2)", parse_expr("m.b = 2").to_string());
+
+        // in statement position only `{ key:` starts a map
+        let statements = parse("{ a: 1 }\n{ a }\n{}\n").unwrap();
+        assert_eq!("(map (\"a\" 1))", statements[0].to_string());
+        assert!(matches!(statements[1], Stmt::Block { .. }));
+        assert!(matches!(statements[2], Stmt::Block { .. }));
+        let statements = parse("let m = {}\n").unwrap();
+        assert_eq!("(let m (map))", statements[0].to_string());
+
+        let err = parse_error("let m = { 1: 2 }");
+        assert_eq!("expected map key, found '1'", err.message());
+    }
+
+    #[test]
+    fn logical_test() {
+        assert_eq!(
+            "(|| (&& (== a 1) (== b 2)) c)",
+            parse_expr("a == 1 && b == 2 || c").to_string()
+        );
+        assert_eq!("(|| a (&& b c))", parse_expr("a || b && c").to_string());
+        assert_eq!(
+            "(&& (! (group (== a b))) (! (! c)))",
+            parse_expr("!(a == b) && !!c").to_string()
+        );
+        assert_eq!("(= x (|| a b))", parse_expr("x = a || b").to_string());
+    }
+
+    #[test]
+    fn call_test() {
+        assert_eq!("(call f)", parse_expr("f()").to_string());
+        assert_eq!(
+            "(call add_two (+ 1 2) b: 3)",
+            parse_expr("add_two(1 + 2, b: 3)").to_string()
+        );
+        assert_eq!("(call (call f 1) 2)", parse_expr("f(1)(2)").to_string());
+        assert_eq!(
+            "(- (call f a b))",
+            parse_expr("-f(\n  a,\n  b,\n)").to_string()
+        );
+        assert_eq!(Span::new(0, 10), parse_expr("f(1)(2, 3)").span());
+
+        let err = parse_error("f(a: 1, 2)");
+        assert_eq!(
+            "expected named argument, found positional argument",
+            err.message()
+        );
+        assert_eq!(Span::new(8, 9), err.span);
+
+        let err = parse_error("f(1 2)");
+        assert_eq!("expected ')' after arguments, found '2'", err.message());
+    }
+
+    #[test]
+    fn if_test() {
+        let stmts = parse(
+            "if (a == b), { print(\"equal\") }\nif a { 1 } elsif b { 2 }\nelse {\n  3\n}\nlet x = if c { 1 } else { 2 }",
+        )
+        .unwrap();
+        assert_eq!(3, stmts.len());
+        assert_eq!(
+            "(if (group (== a b)) (then (print \"equal\")))",
+            stmts[0].to_string()
+        );
+        assert_eq!(
+            "(if a (then 1) (else (if b (then 2) (else 3))))",
+            stmts[1].to_string()
+        );
+        assert_eq!(Span::new(32, 69), stmts[1].span());
+        assert_eq!("(let x (if c (then 1) (else 2)))", stmts[2].to_string());
+
+        let err = parse_error("if a\n{ 1 }");
+        assert_eq!("expected '{', found newline", err.message());
+    }
+
+    #[test]
+    fn case_test() {
+        let source = "case value {
+    1, 2: { print(\"low\") },
+    -1: \"negative\"
+    none: { print(\"none\") }
+}";
+        let stmts = parse(source).unwrap();
+        assert_eq!(
+            "(case value ((1 2) (print \"low\")) (((- 1)) \"negative\") (none (print \"none\")))",
+            stmts[0].to_string()
+        );
+        assert_eq!(Span::new(0, source.len()), stmts[0].span());
+
+        let err = parse_error("case x { y: 1 }");
+        assert_eq!("expected literal pattern, found 'y'", err.message());
+
+        let err = parse_error("case x { none: 1, none: 2 }");
+        assert_eq!(
+            "expected case arm, found a second 'none' arm",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn case_warning_test() {
+        let warnings = |input: &str| {
+            let mut parser = parser::new(lexer::new(input).parse().unwrap());
+            parser.parse().unwrap();
+            parser.warnings().to_vec()
+        };
+
+        let found = warnings("let x = 1\ncase x == 1 { true: 1 }");
+        assert_eq!(1, found.len());
+        assert_eq!("case on a bool does not handle 'false'", found[0].message);
+        assert_eq!((2, 1), (found[0].line, found[0].column));
+
+        assert!(warnings("case b { true: 1, false: 2 }").is_empty());
+        assert!(warnings("case b { false: 1, none: 2 }").is_empty());
+        assert!(warnings("case n { 1: 1 }").is_empty());
+    }
+
+    #[test]
+    fn for_test() {
+        let stmts = parse(
+            "for v in values {\n  print v\n}\nfor (v, index) in 0..n + 1 { continue }\nfor c in \"abc\" { if c == \"b\" { break } }",
+        )
+        .unwrap();
+        assert_eq!("(for v values (block (print v)))", stmts[0].to_string());
+        assert_eq!(
+            "(for (v, index) (.. 0 (+ n 1)) (block (continue)))",
+            stmts[1].to_string()
+        );
+        assert_eq!(
+            "(for c \"abc\" (block (if (== c \"b\") (then (break)))))",
+            stmts[2].to_string()
+        );
+
+        let err = parse_error("break");
+        assert_eq!("'break' outside of a loop", err.message());
+
+        // a function body is not part of the loop around its declaration
+        let err = parse_error("for i in 0..2 {\n  def f { continue }\n}");
+        assert_eq!("'continue' outside of a loop", err.message());
+
+        let err = parse_error("for v values { }");
+        assert_eq!(
+            "expected 'in' after loop variable, found 'values'",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn error_handling_test() {
+        let stmts = parse(
+            "raise error(\"not found\", path, kind: \"io\")\nlet x = handle {\n  read(path)\n} error \"io\" e {\n  none\n} error (message, data) {\n  raise message\n} error { 0 }",
+        )
+        .unwrap();
+        assert_eq!(
+            "(raise (error \"not found\" path kind: \"io\"))",
+            stmts[0].to_string()
+        );
+        assert_eq!(
+            "(let x (handle (block (call read path)) (error \"io\" e none) (error (message, data) (raise message)) (error 0)))",
+            stmts[1].to_string()
+        );
+
+        // handlers have to follow the closing brace on the same line
+        let err = parse_error("handle { 1 }\nerror e { 2 }");
+        assert_eq!(
+            "expected 'error' clause after handle block, found newline",
+            err.message()
+        );
+
+        let err = parse_error("handle { 1 } error (a, b, c, d) { 2 }");
+        assert_eq!("expected at most 3 names, found 4", err.message());
+    }
+
+    #[test]
+    fn module_test() {
+        let stmts = parse(
+            "import io\nimport lib.strings\nmodule mymodule {\n    def f() { 1 }\n    defp g() { 2 }\n}\nmymodule.f()\nio.files.open(path).close",
+        )
+        .unwrap();
+        assert_eq!("(import io)", stmts[0].to_string());
+        assert_eq!("(import lib.strings)", stmts[1].to_string());
+        assert_eq!(
+            "(module mymodule (def f () 1) (defp g () 2))",
+            stmts[2].to_string()
+        );
+        assert_eq!("(call (. mymodule f))", stmts[3].to_string());
+        assert_eq!(
+            "(. (call (. (. io files) open) path) close)",
+            stmts[4].to_string()
+        );
+
+        let err = parse_error("import lib.");
+        assert_eq!(
+            "expected module name after '.', found end of input",
+            err.message()
+        );
+    }
+
+    #[test]
+    fn parse_error_test() {
+        let err = parse_error("(1 + 2");
+        assert_eq!(
+            ParseErrorKind::Unexpected {
+                expected: "')' after expression".to_string(),
+                found: "end of input".to_string()
+            },
+            err.kind
+        );
+
+        let err = parse_error("1 +\n");
+        assert_eq!("expected expression, found newline", err.message());
+
+        let err = parse_error("1 2");
+        assert_eq!("expected end of statement, found '2'", err.message());
+    }
+
+    #[test]
+    fn error_recovery_test() {
+        let source = "1 +\nlet = 3\nprint(\"ok\"\n{\n  2 *\n  let y = 1\n}\n}\nprint 4";
+        let errors = parse(source).unwrap_err();
+
+        let messages: Vec<String> = errors.iter().map(|e| e.message()).collect();
+        assert_eq!(
+            vec![
+                "expected expression, found newline",
+                "expected variable name after 'let', found '='",
+                "expected ')' after arguments, found newline",
+                "expected expression, found newline",
+                "expected statement, found '}'",
+            ],
+            messages
+        );
 
-        println!("Expression value: {}", expr.value());
+        // recovering inside a block still closes it, so parsing carries on after it
+        let errors = parse("{\n 1 +\n 2\n}\n3 4").unwrap_err();
+        assert_eq!(2, errors.len());
+        assert_eq!("expected end of statement, found '4'", errors[1].message());
     }
 }
@@ -1,69 +1,116 @@
 #![allow(dead_code)]
 
-pub static ILLEGAL: &'static str = "ILLEGAL";
-pub static EOF: &'static str = "EOF";
-pub static WHITESPACE: &'static str = "WHITESPACE";
-pub static NEWLINE: &'static str = "NEWLINE";
+pub static ILLEGAL: &str = "ILLEGAL";
+pub static EOF: &str = "EOF";
+pub static WHITESPACE: &str = "WHITESPACE";
+pub static NEWLINE: &str = "NEWLINE";
 //single character tokens
-pub static PLUS: &'static str = "+";
-pub static MINUS: &'static str = "-";
-pub static MULTIPLY: &'static str = "*";
-pub static DIVIDE: &'static str = "/";
-pub static MODULO: &'static str = "%";
-pub static LPAREN: &'static str = "(";
-pub static RPAREN: &'static str = ")";
-pub static LBRACKET: &'static str = "[";
-pub static RBRACKET: &'static str = "]";
-pub static DOT: &'static str = ".";
-pub static LBRACE: &'static str = "{";
-pub static RBRACE: &'static str = "}";
-pub static COMMA: &'static str = ",";
-pub static COLON: &'static str = ":";
-pub static COMMENT: &'static str = "//";
+pub static PLUS: &str = "+";
+pub static MINUS: &str = "-";
+pub static MULTIPLY: &str = "*";
+pub static DIVIDE: &str = "/";
+pub static MODULO: &str = "%";
+pub static POWER: &str = "**";
+pub static INT_DIVIDE: &str = "~/";
+pub static LPAREN: &str = "(";
+pub static RPAREN: &str = ")";
+pub static LBRACKET: &str = "[";
+pub static RBRACKET: &str = "]";
+pub static DOT: &str = ".";
+pub static DOT_DOT: &str = "..";
+pub static LBRACE: &str = "{";
+pub static RBRACE: &str = "}";
+pub static COMMA: &str = ",";
+pub static COLON: &str = ":";
+pub static COMMENT: &str = "//";
+pub static BLOCK_COMMENT: &str = "/*";
+pub static DOC_COMMENT: &str = "///";
 //comparators
-pub static GREATER_THAN: &'static str = ">";
-pub static LESSER_THAN: &'static str = "<";
-pub static EQ: &'static str = "==";
-pub static NEQ: &'static str = "!=";
-pub static GREATER_AND_EQ: &'static str = ">=";
-pub static LESSER_AND_EQ: &'static str = "<=";
-pub static ASSIGN: &'static str = "=";
+pub static GREATER_THAN: &str = ">";
+pub static LESSER_THAN: &str = "<";
+pub static EQ: &str = "==";
+pub static NEQ: &str = "!=";
+pub static NOT: &str = "!";
+pub static GREATER_AND_EQ: &str = ">=";
+pub static LESSER_AND_EQ: &str = "<=";
+pub static ASSIGN: &str = "=";
+pub static PLUS_ASSIGN: &str = "+=";
+pub static MINUS_ASSIGN: &str = "-=";
+pub static MULTIPLY_ASSIGN: &str = "*=";
+pub static DIVIDE_ASSIGN: &str = "/=";
+pub static MODULO_ASSIGN: &str = "%=";
 //Keywords
-pub static PRINT: &'static str = "print";
-pub static LET: &'static str = "let";
-pub static VAR: &'static str = "var";
-pub static DEF: &'static str = "def";
-pub static DEFP: &'static str = "defp";
-pub static MODULE: &'static str = "module";
-pub static FOR: &'static str = "for";
-pub static IF: &'static str = "if";
-pub static ELSE: &'static str = "else";
-pub static ELSIF: &'static str = "elsif";
-pub static RAISE: &'static str = "raise";
-pub static ERROR: &'static str = "error";
-pub static HANDLE: &'static str = "handle";
-pub static CASE: &'static str = "case";
-pub static NONE: &'static str = "none";
-pub static CONTINUE: &'static str = "continue";
-pub static BREAK: &'static str = "break";
-pub static TRUE: &'static str = "true";
-pub static FALSE: &'static str = "false";
-pub static OR: &'static str = "||";
-pub static AND: &'static str = "&&";
-pub static IN: &'static str = "in";
+pub static PRINT: &str = "print";
+pub static LET: &str = "let";
+pub static VAR: &str = "var";
+pub static DEF: &str = "def";
+pub static DEFP: &str = "defp";
+pub static MODULE: &str = "module";
+pub static FOR: &str = "for";
+pub static IF: &str = "if";
+pub static ELSE: &str = "else";
+pub static ELSIF: &str = "elsif";
+pub static RAISE: &str = "raise";
+pub static ERROR: &str = "error";
+pub static HANDLE: &str = "handle";
+pub static CASE: &str = "case";
+pub static NONE: &str = "none";
+pub static CONTINUE: &str = "continue";
+pub static BREAK: &str = "break";
+pub static TRUE: &str = "true";
+pub static FALSE: &str = "false";
+pub static OR: &str = "||";
+pub static AND: &str = "&&";
+pub static IN: &str = "in";
+pub static RETURN: &str = "return";
+pub static IMPORT: &str = "import";
 
-pub static IDENT: &'static str = "IDENT";
-pub static NUMBER: &'static str = "NUMBER";
-pub static STRING: &'static str = "STRING";
+pub static IDENT: &str = "IDENT";
+pub static INT: &str = "INT";
+pub static FLOAT: &str = "FLOAT";
+pub static BYTE: &str = "BYTE";
+pub static STRING: &str = "STRING";
+pub static RAW_STRING: &str = "RAW_STRING";
+pub static STRING_START: &str = "STRING_START";
+pub static STRING_MIDDLE: &str = "STRING_MIDDLE";
+pub static STRING_END: &str = "STRING_END";
 
-pub static UNTERMINATED_STRING: &'static str = "unterminated string";
-pub static INVALID_NUMBER: &'static str = "invalid number value";
+pub static UNTERMINATED_STRING: &str = "unterminated string";
+pub static INVALID_NUMBER: &str = "invalid number value";
+pub static INVALID_ESCAPE: &str = "invalid escape sequence";
+pub static INVALID_BYTE: &str = "invalid byte literal";
+pub static UNTERMINATED_COMMENT: &str = "unterminated block comment";
 
+// `line` and `column` are 1-based and point at the first char of the token,
+// `span` holds its byte offsets in the input
 #[derive(Debug, Copy, Clone)]
-pub struct Token {
+pub struct Token<'a> {
     pub token_type: TokenType,
-    pub val: &'static str,
-    pub col: usize,
+    pub val: &'a str,
+    pub line: usize,
+    pub column: usize,
+    pub span: Span,
+}
+
+// Start (inclusive) and end (exclusive) byte offset of a piece of source
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Span {
+        Span { start, end }
+    }
+
+    // smallest span covering both self and other
+    pub fn to(&self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
 }
 
 #[derive(strum_macros::Display, Debug, PartialEq, Eq, Clone, Copy)]
@@ -71,20 +118,41 @@ pub enum TokenType {
     Illegal,
     UnterminatedString,
     InvalidNumber,
+    InvalidEscape,
+    InvalidByte,
+    UnterminatedComment,
     Whitespace,
     Newline,
     EndOfFile,
     //identifier + literals
     Identifier,
-    Number,
+    Int,
+    Float,
+    // `b'a'`, the value of an ASCII char as an int
+    Byte,
     String,
+    // `r"..."`, whose backslashes are kept as they are
+    RawString,
+    // the text around the expressions embedded in `"a #{x} b #{y} c"`:
+    // `"a ` starts it, ` b ` is a middle part and ` c"` ends it
+    StringStart,
+    StringMiddle,
+    StringEnd,
     Assign,
+    PlusAssign,
+    MinusAssign,
+    MultiplyAssign,
+    DivideAssign,
+    ModuloAssign,
     //Operators
     Plus,
     Minus,
     Multiply,
     Divide,
     Modulo,
+    Power,
+    IntDivide,
+    Not,
     //comparators
     Equal,
     NotEqual,
@@ -101,7 +169,13 @@ pub enum TokenType {
     RightBrace,
     LeftBracket,
     RightBracket,
+    Dot,
+    DotDot,
+    // comments hold their whole text, `/* */` ones may nest and `///` documents
+    // the def or module after it
     Comment,
+    BlockComment,
+    DocComment,
     //Keywords
     Def,
     Defp,
@@ -118,19 +192,24 @@ pub enum TokenType {
     And,
     None,
     Continue,
+    Break,
     Error,
     Handle,
     Raise,
     In,
+    Return,
+    Import,
     Module,
     Print,
 }
 
-pub fn new_illegal_token() -> Token {
+pub fn new_illegal_token() -> Token<'static> {
     Token {
         token_type: TokenType::Illegal,
         val: ILLEGAL,
-        col: 0,
+        line: 0,
+        column: 0,
+        span: Span::default(),
     }
 }
 
@@ -142,14 +221,28 @@ impl TokenType {
             TokenType::Newline => NEWLINE,
             TokenType::EndOfFile => EOF,
             TokenType::Identifier => IDENT,
-            TokenType::Number => NUMBER,
+            TokenType::Int => INT,
+            TokenType::Float => FLOAT,
+            TokenType::Byte => BYTE,
             TokenType::String => STRING,
+            TokenType::RawString => RAW_STRING,
+            TokenType::StringStart => STRING_START,
+            TokenType::StringMiddle => STRING_MIDDLE,
+            TokenType::StringEnd => STRING_END,
             TokenType::Assign => ASSIGN,
+            TokenType::PlusAssign => PLUS_ASSIGN,
+            TokenType::MinusAssign => MINUS_ASSIGN,
+            TokenType::MultiplyAssign => MULTIPLY_ASSIGN,
+            TokenType::DivideAssign => DIVIDE_ASSIGN,
+            TokenType::ModuloAssign => MODULO_ASSIGN,
             TokenType::Plus => PLUS,
             TokenType::Minus => MINUS,
             TokenType::Multiply => MULTIPLY,
             TokenType::Divide => DIVIDE,
             TokenType::Modulo => MODULO,
+            TokenType::Power => POWER,
+            TokenType::IntDivide => INT_DIVIDE,
+            TokenType::Not => NOT,
             TokenType::Equal => EQ,
             TokenType::NotEqual => NEQ,
             TokenType::GreaterThan => GREATER_THAN,
@@ -164,7 +257,11 @@ impl TokenType {
             TokenType::RightBrace => RBRACE,
             TokenType::LeftBracket => LBRACKET,
             TokenType::RightBracket => RBRACKET,
+            TokenType::Dot => DOT,
+            TokenType::DotDot => DOT_DOT,
             TokenType::Comment => COMMENT,
+            TokenType::BlockComment => BLOCK_COMMENT,
+            TokenType::DocComment => DOC_COMMENT,
             TokenType::Def => DEF,
             TokenType::Defp => DEFP,
             TokenType::Let => LET,
@@ -180,15 +277,21 @@ impl TokenType {
             TokenType::And => AND,
             TokenType::None => NONE,
             TokenType::Continue => CONTINUE,
+            TokenType::Break => BREAK,
             TokenType::Error => ERROR,
             TokenType::Handle => HANDLE,
             TokenType::Raise => RAISE,
             TokenType::In => IN,
+            TokenType::Return => RETURN,
+            TokenType::Import => IMPORT,
             TokenType::Print => PRINT,
             TokenType::Module => MODULE,
 
             TokenType::UnterminatedString => UNTERMINATED_STRING,
             TokenType::InvalidNumber => INVALID_NUMBER,
+            TokenType::InvalidEscape => INVALID_ESCAPE,
+            TokenType::InvalidByte => INVALID_BYTE,
+            TokenType::UnterminatedComment => UNTERMINATED_COMMENT,
         }
     }
 }
@@ -0,0 +1,221 @@
+use crate::ast::FunctionDecl;
+use crate::environment::Environment;
+use crate::interpreter::{RuntimeError, SourceFile};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+// Runtime values produced by the interpreter
+#[derive(Debug, Clone)]
+pub enum Value {
+    Int(i64),
+    Float(f64),
+    String(String),
+    Bool(bool),
+    None,
+    Function(Rc<Function>),
+    // whole numbers from start up to, but excluding, end
+    Range(i64, i64),
+    // shared until one of the holders changes it, see `Rc::make_mut`
+    List(Rc<Vec<Value>>),
+    Map(Rc<Map>),
+    Error(Rc<RuntimeError>),
+    Module(Rc<Module>),
+}
+
+// A function declaration together with the scope it was declared in
+pub struct Function {
+    pub declaration: Rc<FunctionDecl>,
+    pub closure: Rc<RefCell<Environment>>,
+    pub file: Option<Rc<SourceFile>>, // none when declared in the main script
+}
+
+// The members defined by a `module` block or an imported file
+#[derive(Debug, PartialEq)]
+pub struct Module {
+    pub name: String,
+    pub members: HashMap<String, Value>,
+}
+
+// String keyed map that remembers the order its keys were first inserted in
+#[derive(Debug, Clone, Default)]
+pub struct Map {
+    entries: Vec<(String, Value)>,
+    positions: HashMap<String, usize>,
+}
+
+impl Map {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.positions.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.positions.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    // a key that is already present keeps its place
+    pub fn insert(&mut self, key: String, value: Value) {
+        match self.positions.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.positions.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        let i = self.positions.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.positions.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|(key, _)| key)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(String, Value)> {
+        self.entries.iter()
+    }
+}
+
+// maps with the same entries are equal whatever order they were inserted in
+impl PartialEq for Map {
+    fn eq(&self, other: &Map) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl Function {
+    pub fn name(&self) -> &str {
+        &self.declaration.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.declaration.params.len()
+    }
+}
+
+// functions are only equal to themselves
+impl PartialEq for Function {
+    fn eq(&self, other: &Function) -> bool {
+        Rc::ptr_eq(&self.declaration, &other.declaration)
+            && Rc::ptr_eq(&self.closure, &other.closure)
+    }
+}
+
+// the closure may contain the function itself, so it is left out
+impl fmt::Debug for Function {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Function({})", self.name())
+    }
+}
+
+impl Value {
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::String(_) => "string",
+            Value::Bool(_) => "bool",
+            Value::None => "none",
+            Value::Function(_) => "func",
+            Value::Range(..) => "range",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Error(_) => "error",
+            Value::Module(_) => "mod",
+        }
+    }
+
+    // the type name with its article, for messages such as "cannot call an int"
+    pub fn a_type_name(&self) -> String {
+        match self {
+            Value::Int(_) | Value::Error(_) => format!("an {}", self.type_name()),
+            _ => format!("a {}", self.type_name()),
+        }
+    }
+
+    // `false` and `none` are falsy, every other value is truthy
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Bool(false) | Value::None)
+    }
+}
+
+// ints and floats are compared by value, so `1 == 1.0`
+impl PartialEq for Value {
+    fn eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b,
+            (Value::Int(a), Value::Float(b)) | (Value::Float(b), Value::Int(a)) => *a as f64 == *b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::None, Value::None) => true,
+            (Value::Function(a), Value::Function(b)) => a == b,
+            (Value::Range(a, b), Value::Range(c, d)) => a == c && b == d,
+            (Value::List(a), Value::List(b)) => a == b,
+            (Value::Map(a), Value::Map(b)) => a == b,
+            (Value::Error(a), Value::Error(b)) => a == b,
+            (Value::Module(a), Value::Module(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{}", n),
+            // `{:?}` keeps the fraction of whole floats, `2.0` rather than `2`
+            Value::Float(n) => write!(f, "{:?}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::None => write!(f, "none"),
+            Value::Function(function) => write!(f, "<def {}>", function.name()),
+            Value::Range(start, end) => write!(f, "{}..{}", start, end),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_element(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(map) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in map.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{:?}: ", key)?;
+                    write_element(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Error(err) => write!(f, "<error {}: {}>", err.kind, err.message),
+            Value::Module(module) => write!(f, "<module {}>", module.name),
+        }
+    }
+}
+
+// strings inside lists and maps are quoted, so that `["1"]` and `[1]` print differently
+fn write_element(f: &mut fmt::Formatter, value: &Value) -> fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "{:?}", s),
+        value => write!(f, "{}", value),
+    }
+}